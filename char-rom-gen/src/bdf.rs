@@ -3,32 +3,313 @@ use anyhow::{
 	Context,
 	Result,
 };
-use std::collections::HashMap;
+use std::collections::{
+	BTreeMap,
+	HashMap,
+};
 
+#[derive(Clone)]
 pub struct Font {
+	/// The `FONT` name, usually an XLFD
+	pub name: String,
+	/// Height of a character cell, `ascent + descent`
 	pub height: usize,
+	/// Width of a character cell
 	pub width: usize,
+	/// Rows in the cell above the baseline
+	pub ascent: usize,
+	/// Rows in the cell below the baseline
+	pub descent: usize,
+	/// The `FONTBOUNDINGBOX`
+	pub bounding_box: BoundingBox,
+	/// The `STARTPROPERTIES` block
+	pub properties: BTreeMap<String, Property>,
 	pub glyphs: HashMap<usize, Glyph>,
 	pub blank: Glyph,
 }
 
+/// Glyph bitmap placed in a full character cell.
+///
+/// Each row holds `width` bits, the left most pixel in the most significant bit.
 #[derive(Clone)]
 pub struct Glyph {
+	/// The `STARTCHAR` glyph name
+	pub name: String,
 	pub height: usize,
 	pub width: usize,
 	pub rows: Vec<usize>,
+	/// Horizontal advance, the `DWIDTH` x component
+	pub advance: isize,
+}
+
+/// A `FONTBOUNDINGBOX` or `BBX`, offsets are from the origin on the baseline
+/// to the bottom left corner.
+#[derive(Clone, Copy)]
+pub struct BoundingBox {
+	pub width: usize,
+	pub height: usize,
+	pub x: isize,
+	pub y: isize,
+}
+
+/// Value of a BDF property, either an integer or a (quoted) string.
+#[derive(Clone)]
+pub enum Property {
+	Integer(isize),
+	String(String),
 }
 
 pub fn parse(lines: &str) -> Result<Font> {
 	Font::parse(lines)
 }
 
+impl BoundingBox {
+	fn parse(keyword: &str, values: &str) -> Result<Self> {
+		let mut values = values.split_ascii_whitespace();
+		let Some(width) = values.next() else {
+			bail!("Missing '{keyword}' <width>");
+		};
+		let Some(height) = values.next() else {
+			bail!("Missing '{keyword}' <height>");
+		};
+		let Some(x) = values.next() else {
+			bail!("Missing '{keyword}' <x-offset>");
+		};
+		let Some(y) = values.next() else {
+			bail!("Missing '{keyword}' <y-offset>");
+		};
+		Ok(BoundingBox {
+			width: width.parse().with_context(|| format!("{keyword} <width>"))?,
+			height: height.parse().with_context(|| format!("{keyword} <height>"))?,
+			x: x.parse().with_context(|| format!("{keyword} <x-offset>"))?,
+			y: y.parse().with_context(|| format!("{keyword} <y-offset>"))?,
+		})
+	}
+}
+
+impl Property {
+	fn parse(value: &str) -> Self {
+		if let Some(quoted) = value.strip_prefix('"') {
+			let quoted = quoted.strip_suffix('"').unwrap_or(quoted);
+			Property::String(quoted.replace("\"\"", "\""))
+		} else if let Ok(integer) = value.parse() {
+			Property::Integer(integer)
+		} else {
+			Property::String(value.to_owned())
+		}
+	}
+}
+
+impl std::fmt::Display for Property {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Property::Integer(integer) =>
+				write!(f, "{integer}"),
+			Property::String(string) =>
+				write!(f, "{string}"),
+		}
+	}
+}
+
+impl Glyph {
+	/// An empty `width` x `height` glyph.
+	pub fn blank(width: usize, height: usize) -> Glyph {
+		Glyph {
+			name: String::from("blank"),
+			height,
+			width,
+			rows: vec![0; height],
+			advance: width as isize,
+		}
+	}
+
+	/// A glyph drawn as ASCII art, `#` for a set pixel and `.` for a clear
+	/// one, all lines the same width.
+	pub fn from_art(name: &str, lines: &[&str]) -> Result<Glyph> {
+		let width = lines.first().map_or(0, |line| line.chars().count());
+		let mut glyph = Glyph::blank(width, lines.len());
+		glyph.name = name.to_owned();
+		for (y, line) in lines.iter().enumerate() {
+			if line.chars().count() != width {
+				bail!("{name}: row {y} is {} pixels wide, expecting {width}", line.chars().count());
+			}
+			for (x, pixel) in line.chars().enumerate() {
+				match pixel {
+					'#' => glyph.set(x, y, true),
+					'.' => {}
+					_ => bail!("{name}: row {y} has '{pixel}', expecting '#' or '.'"),
+				}
+			}
+		}
+		Ok(glyph)
+	}
+
+	/// Bits of a full row.
+	pub fn mask(&self) -> usize {
+		if self.width == 0 {
+			0
+		} else {
+			usize::MAX >> (usize::BITS as usize - self.width)
+		}
+	}
+
+	pub fn get(&self, x: usize, y: usize) -> bool {
+		x < self.width && y < self.height && (self.rows[y] >> (self.width - 1 - x)) & 1 != 0
+	}
+
+	pub fn set(&mut self, x: usize, y: usize, value: bool) {
+		if x < self.width && y < self.height {
+			let bit = 1 << (self.width - 1 - x);
+			if value {
+				self.rows[y] |= bit;
+			} else {
+				self.rows[y] &= !bit;
+			}
+		}
+	}
+
+	/// Row `y` of `other` placed with its left edge at column `x`, clipped
+	/// to this glyph.
+	fn placed(&self, other: &Glyph, x: isize, y: usize) -> usize {
+		let row = other.rows[y] & other.mask();
+		let shift = self.width as isize - x - other.width as isize;
+		let row = if shift >= 0 {
+			row.checked_shl(shift as u32).unwrap_or(0)
+		} else {
+			row.checked_shr(-shift as u32).unwrap_or(0)
+		};
+		row & self.mask()
+	}
+
+	/// Combine `other` with its top left corner at `x`, `y` into this glyph
+	/// row by row, clipping at the edges.
+	fn combine(mut self, other: &Glyph, x: isize, y: isize, op: fn(usize, usize) -> usize) -> Glyph {
+		for i in 0..other.height {
+			let Ok(target) = usize::try_from(y + i as isize) else {
+				continue;
+			};
+			if target < self.height {
+				let row = self.placed(other, x, i);
+				self.rows[target] = op(self.rows[target], row);
+			}
+		}
+		self
+	}
+
+	/// Set the pixels of `other` placed at `x`, `y`.
+	pub fn overlay(self, other: &Glyph, x: isize, y: isize) -> Glyph {
+		self.combine(other, x, y, |a, b| a | b)
+	}
+
+	/// Toggle the pixels of `other` placed at `x`, `y`, so that overlapping
+	/// pixels stand out.
+	pub fn xor(self, other: &Glyph, x: isize, y: isize) -> Glyph {
+		self.combine(other, x, y, |a, b| a ^ b)
+	}
+
+	/// Move the bitmap right by `x` and down by `y` within the cell.
+	pub fn offset(&self, x: isize, y: isize) -> Glyph {
+		let blank = Glyph {
+			rows: vec![0; self.height],
+			..self.clone()
+		};
+		blank.overlay(self, x, y)
+	}
+
+	/// Flip left to right.
+	pub fn mirror(&self) -> Glyph {
+		let mut mirrored = Glyph {
+			rows: vec![0; self.height],
+			..self.clone()
+		};
+		for y in 0..self.height {
+			for x in 0..self.width {
+				mirrored.set(self.width - 1 - x, y, self.get(x, y));
+			}
+		}
+		mirrored
+	}
+
+	/// Flip top to bottom.
+	pub fn flip(&self) -> Glyph {
+		let mut flipped = self.clone();
+		flipped.rows.reverse();
+		flipped
+	}
+
+	/// Swap set and clear pixels.
+	pub fn invert(&self) -> Glyph {
+		let mask = self.mask();
+		let mut inverted = self.clone();
+		for row in inverted.rows.iter_mut() {
+			*row = !*row & mask;
+		}
+		inverted
+	}
+
+	/// Thicken vertical strokes by one pixel to the right, as terminals do
+	/// for bold without a bold font.
+	pub fn embolden(&self) -> Glyph {
+		let mask = self.mask();
+		let mut bold = self.clone();
+		for row in bold.rows.iter_mut() {
+			*row = (*row | (*row >> 1)) & mask;
+		}
+		bold
+	}
+
+	/// Nearest neighbour scaling by whole factors, up to `usize::BITS` wide.
+	pub fn scale(&self, x: usize, y: usize) -> Glyph {
+		let mut scaled = Glyph::blank(self.width * x, self.height * y);
+		scaled.name = self.name.clone();
+		scaled.advance = self.advance * x as isize;
+		for row in 0..scaled.height {
+			for column in 0..scaled.width {
+				scaled.set(column, row, self.get(column / x, row / y));
+			}
+		}
+		scaled
+	}
+
+	/// The `width` x `height` part with its top left corner at `x`, `y`.
+	pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Glyph {
+		let mut cropped = Glyph::blank(width, height);
+		cropped.name = self.name.clone();
+		cropped.xor(self, -(x as isize), -(y as isize))
+	}
+}
+
 impl Font {
+	/// An empty font of `width` x `height` cells with `ascent` rows above
+	/// the baseline, `width` being 1 to `usize::BITS`.
+	pub fn new(name: &str, width: usize, height: usize, ascent: usize) -> Result<Self> {
+		if width == 0 || width > usize::BITS as usize {
+			bail!("Font width {width}, expecting 1 to {}", usize::BITS);
+		}
+		let descent = height.saturating_sub(ascent);
+		Ok(Font {
+			name: name.to_owned(),
+			width,
+			height,
+			ascent,
+			descent,
+			bounding_box: BoundingBox {
+				width,
+				height,
+				x: 0,
+				y: -(descent as isize),
+			},
+			properties: BTreeMap::new(),
+			glyphs: HashMap::new(),
+			blank: Glyph::blank(width, height),
+		})
+	}
+
 	fn parse(lines: &str) -> Result<Self> {
-		let mut lines = Lines::new(&lines);
-		let mut got_size = false;
-		let mut height = 0usize;
-		let mut width = 0usize;
+		let mut lines = Lines::new(lines);
+		let mut name = String::new();
+		let mut bounding_box = None;
+		let mut properties = BTreeMap::new();
 		let mut glyphs = HashMap::new();
 
 		lines.next()?;
@@ -40,33 +321,28 @@ impl Font {
 
 		// Global Font information
 		loop {
-			let Some((name, values)) = lines.line.split_once(' ') else {
-				bail!("Expecting Global Font information, found '{}'", lines.line);
-			};
-			if name == "CHARS" {
-				if !got_size {
-					bail!("Missing 'FONTBOUNDINGBOX' before 'CHARS'");
-				}
+			let (keyword, values) = lines.keyword();
+			if keyword == "CHARS" {
 				lines.next()?;
 				break;
 			}
-			if name == "FONTBOUNDINGBOX" {
-				got_size = true;
-				let mut values = values.split(' ');
-				let Some(value) = values.next() else {
-					bail!("Missing 'FONTBOUNDINGBOX' <width>");
-				};
-				width = value.parse().context("FONTBOUNDINGBOX <width>")?;
-				let Some(value) = values.next() else {
-					bail!("Missing 'FONTBOUNDINGBOX' <height>");
-				};
-				height = value.parse().context("FONTBOUNDINGBOX <height>")?;
+			if keyword == "FONT" {
+				name = values.to_owned();
 				lines.next()?;
 				continue;
 			}
-			if name == "STARTPROPERTIES" {
+			if keyword == "FONTBOUNDINGBOX" {
+				bounding_box = Some(BoundingBox::parse(keyword, values)?);
+				lines.next()?;
+				continue;
+			}
+			if keyword == "STARTPROPERTIES" {
 				lines.next()?;
 				while !lines.line.starts_with("ENDPROPERTIES") {
+					let (keyword, value) = lines.keyword();
+					if !keyword.is_empty() {
+						properties.insert(keyword.to_owned(), Property::parse(value.trim()));
+					}
 					lines.next()?;
 				}
 				lines.next()?;
@@ -75,6 +351,24 @@ impl Font {
 			// ignore
 			lines.next()?;
 		}
+		let Some(bounding_box) = bounding_box else {
+			bail!("Missing 'FONTBOUNDINGBOX' before 'CHARS'");
+		};
+		let ascent = match properties.get("FONT_ASCENT") {
+			Some(Property::Integer(ascent)) if *ascent >= 0 => *ascent as usize,
+			Some(_) => bail!("FONT_ASCENT must be a non-negative integer"),
+			None => (bounding_box.height as isize + bounding_box.y).max(0) as usize,
+		};
+		let descent = match properties.get("FONT_DESCENT") {
+			Some(Property::Integer(descent)) if *descent >= 0 => *descent as usize,
+			Some(_) => bail!("FONT_DESCENT must be a non-negative integer"),
+			None => (-bounding_box.y).max(0) as usize,
+		};
+		let width = bounding_box.width;
+		let height = ascent + descent;
+		if width == 0 || width > usize::BITS as usize {
+			bail!("FONTBOUNDINGBOX <width> {width}, expecting 1 to {}", usize::BITS);
+		}
 
 		// Characters / Glyphs
 		loop {
@@ -85,94 +379,165 @@ impl Font {
 			if lines.line == "ENDFONT" {
 				break;
 			}
-			let Some(("STARTCHAR", code_point)) = lines.line.split_once(' ') else {
+			let Some(("STARTCHAR", name)) = lines.line.split_once(' ') else {
 				bail!("Expecting 'STARTCHAR' found '{}'", lines.line);
 			};
-			let Some(code_point) = code_point.strip_prefix("U+") else {
-				bail!("Expecting STARTCHAR 'U+' found '{}'", code_point);
-			};
-			let code_point = u32::from_str_radix(code_point, 16).context("STARTCHAR")? as usize;
-			let mut glyph = Glyph {
-				width: 0,
-				height: 0,
-				rows: Vec::new(),
-			};
-			got_size = false;
+			let mut code_point = None;
+			let mut bbx = None;
+			let mut advance = width as isize;
 			lines.next()?;
 			while !lines.line.starts_with("BITMAP") {
-				let Some((name, values)) = lines.line.split_once(' ') else {
-					bail!("Expecting Global Font information, found '{}'", lines.line);
-				};
-				if name == "BBX" {
-					got_size = true;
-					let mut values = values.split(' ');
-					let Some(value) = values.next() else {
-						bail!("Missing 'BBX' <width>");
-					};
-					glyph.width = value.parse().context("BBX <width>")?;
-					let Some(value) = values.next() else {
-						bail!("Missing 'BBX' <height>");
+				let (keyword, values) = lines.keyword();
+				if keyword == "ENCODING" {
+					code_point = Some(encoding(name, values)?);
+				}
+				if keyword == "BBX" {
+					bbx = Some(BoundingBox::parse(keyword, values)?);
+				}
+				if keyword == "DWIDTH" {
+					let Some(value) = values.split_ascii_whitespace().next() else {
+						bail!("Missing 'DWIDTH' <dwx0>");
 					};
-					glyph.height = value.parse().context("BBX <height>")?;
-					lines.next()?;
-					continue;
+					advance = value.parse().context("DWIDTH <dwx0>")?;
 				}
-				// ignore
+				// ignore others
 				lines.next()?;
 			}
-			if !got_size {
+			// without an ENCODING fall back to a "U+<hex>" glyph name
+			let code_point = match code_point {
+				Some(code_point) => code_point,
+				None => name.strip_prefix("U+")
+					.and_then(|hex| usize::from_str_radix(hex, 16).ok()),
+			};
+			let Some(bbx) = bbx else {
 				bail!("Missing 'BBX' before 'BITMAP'");
-			}
+			};
 			lines.next()?;
-			for _ in 0..glyph.height {
+			let mut glyph = Glyph::blank(width, height);
+			glyph.name = name.to_owned();
+			glyph.advance = advance;
+			// position of the BBX's top right corner in the cell
+			let right = (bbx.x - bounding_box.x) + bbx.width as isize;
+			let top = ascent as isize - (bbx.y + bbx.height as isize);
+			let mask = glyph.mask();
+			for i in 0..bbx.height {
 				let wide = lines.line.len() * 4;
 				if wide > usize::BITS as usize {
 					bail!("BITMAP wider than usize, '{}'", lines.line);
 				}
-				if wide < glyph.width {
-					bail!("BITMAP narrower than BBX {}, '{}'", glyph.width, lines.line);
+				if wide < bbx.width {
+					bail!("BITMAP narrower than BBX {}, '{}'", bbx.width, lines.line);
 				}
 				let mut row = usize::from_str_radix(lines.line, 16).context("BITMAP")?;
-				if wide > glyph.width {
-					row >>= wide - glyph.width;
+				if wide > bbx.width {
+					row >>= wide - bbx.width;
 				}
 				lines.next()?;
-				glyph.rows.push(row);
+				// clip anything outside of the cell
+				let shift = width as isize - right;
+				let row = if shift >= 0 {
+					row.checked_shl(shift as u32).unwrap_or(0)
+				} else {
+					row.checked_shr(-shift as u32).unwrap_or(0)
+				};
+				let y = top + i as isize;
+				if y >= 0 && (y as usize) < height {
+					glyph.rows[y as usize] = row & mask;
+				}
 			}
 			if !lines.line.starts_with("ENDCHAR") {
 				bail!("Expecing 'ENDCHAR', found '{}'", lines.line);
 			}
 			lines.next()?;
-			glyphs.insert(code_point, glyph);
+			// unencoded glyphs can not be addressed, drop them
+			if let Some(code_point) = code_point {
+				glyphs.insert(code_point, glyph);
+			}
 		}
 
-		let mut blank = Glyph {
-			width,
-			height,
-			rows: Vec::new(),
-		};
-		for _ in 0..height {
-			blank.rows.push(0);
-		}
+		let blank = Glyph::blank(width, height);
 
 		Ok(Font {
+			name,
 			width,
 			height,
+			ascent,
+			descent,
+			bounding_box,
+			properties,
 			glyphs,
 			blank,
 		})
 	}
 
+	/// Serialize as BDF 2.1, each glyph as a full cell bitmap in key order.
+	/// `encoding` gives the Unicode code point of the glyph of a key, glyphs
+	/// without one are written as `ENCODING -1` with the key as their
+	/// non-standard code.
+	pub fn write(&self, encoding: impl Fn(usize) -> Option<usize>) -> String {
+		let mut bdf = String::new();
+		let descent = self.descent as isize;
+		let pixel_size = self.pixel_size().unwrap_or(self.height).max(1);
+		bdf.push_str("STARTFONT 2.1\n");
+		if self.name.is_empty() {
+			bdf.push_str(&format!("FONT char-rom-{}x{}\n", self.width, self.height));
+		} else {
+			bdf.push_str(&format!("FONT {}\n", self.name));
+		}
+		bdf.push_str(&format!("SIZE {pixel_size} 75 75\n"));
+		bdf.push_str(&format!("FONTBOUNDINGBOX {} {} {} {}\n",
+			self.width, self.height, self.bounding_box.x, -descent));
+		let mut properties = self.properties.clone();
+		properties.insert("FONT_ASCENT".into(), Property::Integer(self.ascent as isize));
+		properties.insert("FONT_DESCENT".into(), Property::Integer(descent));
+		bdf.push_str(&format!("STARTPROPERTIES {}\n", properties.len()));
+		for (name, value) in &properties {
+			match value {
+				Property::Integer(integer) =>
+					bdf.push_str(&format!("{name} {integer}\n")),
+				Property::String(string) =>
+					bdf.push_str(&format!("{name} \"{}\"\n", string.replace('"', "\"\""))),
+			}
+		}
+		bdf.push_str("ENDPROPERTIES\n");
+		let mut codes: Vec<usize> = self.glyphs.keys().copied().collect();
+		codes.sort();
+		bdf.push_str(&format!("CHARS {}\n", codes.len()));
+		let digits = self.width.div_ceil(8) * 2;
+		let pad = (digits * 4) - self.width;
+		for code in codes {
+			let glyph = &self.glyphs[&code];
+			bdf.push_str(&format!("STARTCHAR {}\n", glyph.name));
+			match encoding(code) {
+				Some(encoding) => bdf.push_str(&format!("ENCODING {encoding}\n")),
+				None => bdf.push_str(&format!("ENCODING -1 {code}\n")),
+			}
+			bdf.push_str(&format!("SWIDTH {} 0\n", glyph.advance * 1000 / pixel_size as isize));
+			bdf.push_str(&format!("DWIDTH {} 0\n", glyph.advance));
+			bdf.push_str(&format!("BBX {} {} {} {}\n",
+				self.width, self.height, self.bounding_box.x, -descent));
+			bdf.push_str("BITMAP\n");
+			for row in &glyph.rows {
+				bdf.push_str(&format!("{:0digits$X}\n", row << pad));
+			}
+			bdf.push_str("ENDCHAR\n");
+		}
+		bdf.push_str("ENDFONT\n");
+		bdf
+	}
+
 	pub fn glyph_add(&mut self, index: usize, mut rows: Vec<usize>) {
 		rows.resize(self.height, 0);
-		let mask = (1 << self.width) - 1;
+		let mask = self.blank.mask();
 		for row in rows.iter_mut() {
 			*row &= mask;
 		}
 		self.glyphs.insert(index, Glyph {
+			name: format!("U+{index:04X}"),
 			width: self.width,
 			height: self.height,
 			rows,
+			advance: self.width as isize,
 		});
 	}
 
@@ -180,13 +545,63 @@ impl Font {
 		self.glyphs.get(&index)
 	}
 
-	pub fn glyph_or_blank(&self, index: usize) -> &Glyph {
+	/// The glyph, otherwise the `DEFAULT_CHAR` glyph, otherwise blank.
+	pub fn glyph_or_default(&self, index: usize) -> &Glyph {
 		if let Some(glyph) = self.glyphs.get(&index) {
 			glyph
+		} else if let Some(glyph) = self.default_char().and_then(|index| self.glyphs.get(&index)) {
+			glyph
 		} else {
 			&self.blank
 		}
 	}
+
+	pub fn property(&self, name: &str) -> Option<&Property> {
+		self.properties.get(name)
+	}
+
+	pub fn property_integer(&self, name: &str) -> Option<isize> {
+		match self.properties.get(name) {
+			Some(Property::Integer(integer)) => Some(*integer),
+			_ => None,
+		}
+	}
+
+	pub fn property_string(&self, name: &str) -> Option<&str> {
+		match self.properties.get(name) {
+			Some(Property::String(string)) => Some(string),
+			_ => None,
+		}
+	}
+
+	pub fn family_name(&self) -> Option<&str> {
+		self.property_string("FAMILY_NAME")
+	}
+
+	pub fn pixel_size(&self) -> Option<usize> {
+		self.property_integer("PIXEL_SIZE")
+			.and_then(|size| usize::try_from(size).ok())
+	}
+
+	pub fn default_char(&self) -> Option<usize> {
+		self.property_integer("DEFAULT_CHAR")
+			.and_then(|index| usize::try_from(index).ok())
+	}
+
+	pub fn copyright(&self) -> Option<&str> {
+		self.property_string("COPYRIGHT")
+	}
+}
+
+/// Code point from `ENCODING <n>`. `ENCODING -1`, optionally followed by a
+/// font specific index, marks an unencoded glyph, which is not a code point.
+fn encoding(name: &str, values: &str) -> Result<Option<usize>> {
+	let Some(value) = values.split_ascii_whitespace().next() else {
+		bail!("Missing 'ENCODING' <n> for '{name}'");
+	};
+	let value: isize = value.parse()
+		.with_context(|| format!("ENCODING <n> for '{name}'"))?;
+	Ok(usize::try_from(value).ok())
 }
 
 struct Lines<'a> {
@@ -202,6 +617,11 @@ impl<'a> Lines<'a> {
 		}
 	}
 
+	/// Split the current line into keyword and (possibly empty) values.
+	fn keyword(&self) -> (&'a str, &'a str) {
+		self.line.split_once(' ').unwrap_or((self.line, ""))
+	}
+
 	fn next(&mut self) -> Result<()> {
 		if let Some(line) = self.lines.next() {
 			self.line = line;
@@ -211,3 +631,90 @@ impl<'a> Lines<'a> {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A font with a `width` x 16 bounding box, 12 rows above the baseline, and
+	/// the glyphs given as BDF text.
+	fn with_glyphs(width: usize, glyphs: &str) -> Result<Font> {
+		parse(&format!("STARTFONT 2.1
+FONT test
+FONTBOUNDINGBOX {width} 16 0 -4
+STARTPROPERTIES 2
+FONT_ASCENT 12
+FONT_DESCENT 4
+ENDPROPERTIES
+CHARS 1
+{glyphs}ENDFONT
+"))
+	}
+
+	#[test]
+	fn bbx_offset() {
+		// a 4x6 box 2 pixels right of the origin and 1 row above the baseline
+		let font = with_glyphs(8, "STARTCHAR box
+ENCODING 65
+BBX 4 6 2 1
+BITMAP
+F0
+90
+90
+90
+90
+F0
+ENDCHAR
+").unwrap();
+		assert_eq!((font.width, font.height, font.ascent), (8, 16, 12));
+		let glyph = font.glyph(65).unwrap();
+		// rows 5..=10 from the top, the baseline below row 11
+		let mut rows = vec![0; 16];
+		rows[5..=10].copy_from_slice(&[0x3C, 0x24, 0x24, 0x24, 0x24, 0x3C]);
+		assert_eq!(glyph.rows, rows);
+	}
+
+	#[test]
+	fn bounding_box_width() {
+		let glyph = "STARTCHAR full
+ENCODING 65
+BBX 64 1 0 0
+BITMAP
+FFFFFFFFFFFFFFFF
+ENDCHAR
+";
+		let mut font = with_glyphs(64, glyph).unwrap();
+		assert_eq!(font.glyph(65).unwrap().rows[11], usize::MAX);
+		font.glyph_add(66, vec![usize::MAX]);
+		assert_eq!(font.glyph(66).unwrap().rows[0], usize::MAX);
+		assert!(with_glyphs(0, "").is_err());
+		assert!(with_glyphs(65, "").is_err());
+		assert!(Font::new("", 0, 16, 12).is_err());
+		assert!(Font::new("", 65, 16, 12).is_err());
+	}
+
+	#[test]
+	fn unencoded() {
+		let font = with_glyphs(8, "STARTCHAR A
+ENCODING 65
+BBX 8 1 0 0
+BITMAP
+FF
+ENDCHAR
+STARTCHAR extra
+ENCODING -1 65
+BBX 8 1 0 0
+BITMAP
+01
+ENDCHAR
+STARTCHAR none
+ENCODING -1
+BBX 8 1 0 0
+BITMAP
+02
+ENDCHAR
+").unwrap();
+		assert_eq!(font.glyphs.len(), 1);
+		assert_eq!(font.glyph(65).unwrap().name, "A");
+	}
+}
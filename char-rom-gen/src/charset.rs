@@ -0,0 +1,264 @@
+//! Character sets, one ROM bank each, mapping ROM codes to the Unicode
+//! glyphs of the primary font.
+//!
+//! A mapping table has one `<code> <code point>` pair of hex numbers per
+//! line, as in the Unicode consortium mapping files, with `#` comments.
+//! Codes not listed keep the glyph of the ASCII bank, the synthesized hex
+//! glyph for the upper half.
+
+use anyhow::{
+    Context,
+    Result,
+    bail,
+};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::bdf::{
+    Font,
+    Glyph,
+};
+
+/// Mapping tables built into the generator, by name.
+pub const BUILTIN: &[(&str, &str)] = &[
+    ("ascii", ""),
+    ("uk", include_str!("../charsets/uk.txt")),
+    ("dec-special-graphics", include_str!("../charsets/dec-special-graphics.txt")),
+    ("cp437", include_str!("../charsets/cp437.txt")),
+    ("latin-1", include_str!("../charsets/latin-1.txt")),
+];
+
+#[derive(Clone)]
+pub struct Charset {
+    pub name: String,
+    /// ROM code to Unicode code point
+    pub map: BTreeMap<usize, usize>,
+}
+
+impl Charset {
+    /// The ASCII bank, the ROM glyphs as they are.
+    pub fn ascii() -> Self {
+        Charset {
+            name: String::from("ascii"),
+            map: BTreeMap::new(),
+        }
+    }
+
+    /// A built in table by name, otherwise a mapping table file.
+    pub fn load(name: &str) -> Result<Self> {
+        if let Some((name, table)) = BUILTIN.iter().find(|(builtin, _)| *builtin == name) {
+            return parse(name, table);
+        }
+        let path = Path::new(name);
+        if !path.exists() {
+            let names: Vec<_> = BUILTIN.iter().map(|(name, _)| *name).collect();
+            bail!("Unknown charset {name}, expecting a file or one of {}", names.join(", "));
+        }
+        let table = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        let name = path.file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| name.to_owned());
+        parse(&name, &table).with_context(|| format!("{}", path.display()))
+    }
+
+    /// Whether only the upper half, codes 0x80..0xFF, is mapped.
+    pub fn is_upper(&self) -> bool {
+        self.map.keys().all(|&code| code >= 0x80)
+    }
+
+    /// Mapped codes and code points with neither a glyph in `unicode` nor a
+    /// synthesized one, which keep the glyph of `base` in [`Charset::bank`].
+    pub fn missing(&self, unicode: &Font) -> Vec<(usize, usize)> {
+        self.map.iter()
+            .filter(|&(_, &code_point)| unicode.glyph(code_point).is_none()
+                && synthesize(code_point, unicode.width, unicode.height).is_none())
+            .map(|(&code, &code_point)| (code, code_point))
+            .collect()
+    }
+
+    /// The 256 glyphs of the bank, those of `base` replaced by the mapped
+    /// glyphs of `unicode`, or synthesized line drawing glyphs the font
+    /// lacks.
+    pub fn bank(&self, base: &Font, unicode: &Font) -> Font {
+        let mut bank = base.clone();
+        for (&code, &code_point) in &self.map {
+            let glyph = match unicode.glyph(code_point) {
+                Some(glyph) => glyph.clone(),
+                None => match synthesize(code_point, unicode.width, unicode.height) {
+                    Some(rows) => Glyph {
+                        name: format!("U+{code_point:04X}"),
+                        height: unicode.height,
+                        width: unicode.width,
+                        rows,
+                        advance: unicode.width as isize,
+                    },
+                    None => continue,
+                },
+            };
+            bank.glyphs.insert(code, glyph);
+        }
+        bank
+    }
+}
+
+pub fn parse(name: &str, table: &str) -> Result<Charset> {
+    let mut map = BTreeMap::new();
+    for (number, line) in table.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let fields: Vec<_> = line.split_whitespace().collect();
+        let [code, code_point] = fields[..] else {
+            bail!("Line {}: expecting '<code> <code point>', found '{line}'", number + 1);
+        };
+        let code = hex(code).with_context(|| format!("Line {}", number + 1))?;
+        let code_point = hex(code_point).with_context(|| format!("Line {}", number + 1))?;
+        if code > 255 {
+            bail!("Line {}: code {code:#X} beyond 0xFF", number + 1);
+        }
+        if map.insert(code, code_point).is_some() {
+            bail!("Line {}: code {code:#04X} mapped twice", number + 1);
+        }
+    }
+    Ok(Charset {
+        name: name.to_owned(),
+        map,
+    })
+}
+
+/// A hex number, with or without a `0x` or `U+` prefix.
+pub fn hex(value: &str) -> Result<usize> {
+    let digits = value.strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .or_else(|| value.strip_prefix("U+"))
+        .unwrap_or(value);
+    usize::from_str_radix(digits, 16)
+        .with_context(|| format!("Invalid hex number '{value}'"))
+}
+
+/// Rows of the light box drawing, scan line, block and shade glyphs of the
+/// DEC Special Graphics set and code page 437, drawn through the middle of
+/// a `width` x `height` cell.
+fn synthesize(code_point: usize, width: usize, height: usize) -> Option<Vec<usize>> {
+    let all = usize::MAX >> (usize::BITS as usize - width);
+    let middle = (height - 1) / 2;
+    let centre = 1 << (width - 1 - ((width - 1) / 2));
+    // up, down, left, right arms of the light box drawings
+    let arms = match code_point {
+        0x2500 => Some((false, false, true, true)),
+        0x2502 => Some((true, true, false, false)),
+        0x250C => Some((false, true, false, true)),
+        0x2510 => Some((false, true, true, false)),
+        0x2514 => Some((true, false, false, true)),
+        0x2518 => Some((true, false, true, false)),
+        0x251C => Some((true, true, false, true)),
+        0x2524 => Some((true, true, true, false)),
+        0x252C => Some((false, true, true, true)),
+        0x2534 => Some((true, false, true, true)),
+        0x253C => Some((true, true, true, true)),
+        _ => None,
+    };
+    if let Some((up, down, left, right)) = arms {
+        let mut rows = vec![0; height];
+        for (row, bits) in rows.iter_mut().enumerate() {
+            if (up && row <= middle) || (down && row >= middle) {
+                *bits |= centre;
+            }
+        }
+        // the horizontal arms include the centre column
+        if left {
+            rows[middle] |= all & !(centre - 1);
+        }
+        if right {
+            rows[middle] |= (centre << 1) - 1;
+        }
+        return Some(rows);
+    }
+    // horizontal scan lines 1, 3, 7 and 9 of 9, line 5 being U+2500
+    let scan_line = match code_point {
+        0x23BA => Some(1),
+        0x23BB => Some(3),
+        0x23BC => Some(7),
+        0x23BD => Some(9),
+        _ => None,
+    };
+    if let Some(line) = scan_line {
+        let mut rows = vec![0; height];
+        rows[((line - 1) * (height - 1)) / 8] = all;
+        return Some(rows);
+    }
+    let left = all & !(all >> (width / 2));
+    let even = all & (usize::MAX / 3);
+    match code_point {
+        // no-break space
+        0x00A0 => Some(vec![0; height]),
+        // upper and lower half, full, left and right half blocks
+        0x2580 => Some((0..height).map(|row| if row < height / 2 { all } else { 0 }).collect()),
+        0x2584 => Some((0..height).map(|row| if row < height / 2 { 0 } else { all }).collect()),
+        0x2588 => Some(vec![all; height]),
+        0x258C => Some(vec![left; height]),
+        0x2590 => Some(vec![all & !left; height]),
+        // light shade, every other pixel of every other row
+        0x2591 => Some((0..height).map(|row| if row % 2 == 0 { even } else { 0 }).collect()),
+        // medium shade, a checkerboard
+        0x2592 => Some((0..height).map(|row| if row % 2 == 0 { even } else { all & !even }).collect()),
+        // dark shade, the inverse of the light shade
+        0x2593 => Some((0..height).map(|row| if row % 2 == 0 { all & !even } else { all }).collect()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rom::tests::rom;
+
+    #[test]
+    fn tables() {
+        let table = "# comment\n\n0x23\t0x00A3\t# POUND SIGN\nC4 U+2500\n";
+        let charset = parse("test", table).unwrap();
+        assert_eq!(charset.map, BTreeMap::from([(0x23, 0xA3), (0xC4, 0x2500)]));
+        assert!(!charset.is_upper());
+        assert!(parse("test", "0x80 0x00C7").unwrap().is_upper());
+        for table in ["0x23", "0x23 0xA3 0x24", "0x100 0x41", "0x23 0xA3\n0x23 0x24", "0xG1 0x41"] {
+            assert!(parse("test", table).is_err(), "{table}");
+        }
+        for (name, _) in BUILTIN {
+            assert!(Charset::load(name).is_ok(), "{name}");
+        }
+        assert_eq!(Charset::load("uk").unwrap().map, BTreeMap::from([(0x23, 0xA3)]));
+    }
+
+    #[test]
+    fn upper() {
+        let (unicode, mut rom) = rom();
+        let hex = rom.glyphs();
+        rom.upper = Some(Charset::load("latin-1").unwrap());
+        let merged = rom.glyphs();
+        let rows = |font: &Font, code| font.glyph(code).unwrap().rows.clone();
+        for code in 0..256 {
+            let expected = match code {
+                // the font has £ and °
+                0xA3 | 0xB0 => rows(&unicode, code),
+                // the no-break space is synthesized
+                0xA0 => vec![0; 20],
+                // ASCII, the C1 controls Latin-1 leaves out and the glyphs
+                // the font lacks keep the hex glyphs
+                _ => rows(&hex, code),
+            };
+            assert_eq!(rows(&merged, code), expected, "{code:#04X}");
+        }
+        assert_ne!(rows(&merged, 0xA3), rows(&hex, 0xA3));
+        let missing = rom.upper.as_ref().unwrap().missing(&unicode);
+        assert!(missing.contains(&(0xA4, 0xA4)) && !missing.contains(&(0xA3, 0xA3)));
+
+        // CP437 box drawing from the font, its letters the font lacks as hex
+        rom.upper = Some(Charset::load("cp437").unwrap());
+        let merged = rom.glyphs();
+        assert_eq!(rows(&merged, 0xC4), rows(&unicode, 0x2500));
+        assert_eq!(rows(&merged, 0x80), rows(&hex, 0x80));
+        assert_eq!(rows(&merged, 0x41), rows(&hex, 0x41));
+    }
+}
@@ -0,0 +1,201 @@
+//! Coverage and sanity checks of the glyphs taken from the fonts.
+
+use crate::bdf::Glyph;
+use crate::rom::Rom;
+
+/// Something wrong or suspicious with a glyph of the ROM.
+#[derive(Debug)]
+pub enum Issue {
+    /// A printable ASCII character missing from a font, the ROM showing the
+    /// `DEFAULT_CHAR` glyph (or blank) or for the bold font the plain glyph
+    MissingAscii {
+        font: String,
+        code: usize,
+    },
+    /// A charset code point the font lacks and that cannot be drawn, the
+    /// bank keeping the ASCII or hex glyph
+    MissingMapped {
+        charset: String,
+        code: usize,
+        code_point: usize,
+    },
+    /// Pixels on the edges of the cell, which run into the neighbouring
+    /// cells on screen
+    Edge {
+        bank: String,
+        code: usize,
+        name: String,
+        edges: Vec<&'static str>,
+    },
+    /// Pixels in the leftmost column, bit 9 of a 10 pixel cell, which is
+    /// usually kept clear as the gap between characters
+    LeftColumn {
+        bank: String,
+        code: usize,
+        name: String,
+        bit: usize,
+    },
+}
+
+impl Issue {
+    /// Whether the ROM would show a wrong glyph, rather than a glyph that
+    /// may look odd.
+    pub fn is_error(&self) -> bool {
+        matches!(self, Issue::MissingAscii { .. } | Issue::MissingMapped { .. })
+    }
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Issue::MissingAscii { font, code } =>
+                write!(f, "{font}: missing printable ASCII {code:#04X} {:?}", char::from(*code as u8)),
+            Issue::MissingMapped { charset, code, code_point } =>
+                write!(f, "{charset}: no glyph for {code:#04X} mapped to U+{code_point:04X}"),
+            Issue::Edge { bank, code, name, edges } =>
+                write!(f, "{bank}: {code:#04X} {name} touches the {} edge", edges.join(", ")),
+            Issue::LeftColumn { bank, code, name, bit } =>
+                write!(f, "{bank}: {code:#04X} {name} uses the leftmost column, bit {bit}"),
+        }
+    }
+}
+
+impl std::error::Error for Issue {}
+
+/// Check the glyphs of the ROM that come from the fonts: printable ASCII,
+/// the upper half and charset mappings. Synthesized glyphs are drawn to fill
+/// the cell on purpose and are not checked for edges.
+pub fn check(rom: &Rom) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    let mut fonts = vec![(rom.unicode.name.clone(), &rom.unicode)];
+    if let Some(bold) = &rom.bold {
+        fonts.push((format!("{} (bold)", bold.name), bold));
+    }
+    for (name, font) in &fonts {
+        for code in 0x20..0x7F {
+            if font.glyph(code).is_none() {
+                issues.push(Issue::MissingAscii {
+                    font: name.clone(),
+                    code,
+                });
+            }
+        }
+    }
+    for charset in rom.upper.iter().chain(&rom.charsets) {
+        for (code, code_point) in charset.missing(&rom.unicode) {
+            issues.push(Issue::MissingMapped {
+                charset: charset.name.clone(),
+                code,
+                code_point,
+            });
+        }
+    }
+
+    // the plain and, with --attributes, bold bank of each charset
+    let per_charset = if rom.bold.is_some() { 2 } else { 1 };
+    for (charset, banks) in rom.charsets.iter().zip(rom.banks().chunks(per_charset)) {
+        let codes: Vec<usize> = (0..256)
+            .filter(|&code| {
+                let code_point = charset.map.get(&code)
+                    .or_else(|| rom.upper.as_ref().and_then(|upper| upper.map.get(&code)))
+                    .copied()
+                    .or((0x20..0x7F).contains(&code).then_some(code));
+                code_point.is_some_and(|code_point| rom.unicode.glyph(code_point).is_some())
+            })
+            .collect();
+        for (bank, glyphs) in banks {
+            for &code in &codes {
+                let glyph = glyphs.glyph_or_default(code);
+                let edges = edges(glyph);
+                if !edges.is_empty() {
+                    issues.push(Issue::Edge {
+                        bank: bank.clone(),
+                        code,
+                        name: glyph.name.clone(),
+                        edges,
+                    });
+                }
+                if (0..glyph.height).any(|y| glyph.get(0, y)) {
+                    issues.push(Issue::LeftColumn {
+                        bank: bank.clone(),
+                        code,
+                        name: glyph.name.clone(),
+                        bit: glyph.width - 1,
+                    });
+                }
+            }
+        }
+    }
+    issues
+}
+
+/// The edges of the cell with set pixels, except the left one reported as
+/// [`Issue::LeftColumn`].
+fn edges(glyph: &Glyph) -> Vec<&'static str> {
+    let mut edges = Vec::new();
+    if glyph.rows.first().is_some_and(|&row| row != 0) {
+        edges.push("top");
+    }
+    if glyph.rows.last().is_some_and(|&row| row != 0) {
+        edges.push("bottom");
+    }
+    if glyph.rows.iter().any(|&row| row & 1 != 0) {
+        edges.push("right");
+    }
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bdf::Font;
+    use crate::rom::Fonts;
+    use crate::rom::tests::font;
+
+    /// A ROM of a 10x20 font with a dot in the middle of each printable
+    /// ASCII glyph, but for `glyphs` and `missing`.
+    fn rom(glyphs: &[(usize, Vec<usize>)], missing: usize) -> Rom {
+        let mut unicode = Font::new("test", 10, 20, 16).unwrap();
+        for code in (0x20..0x7F).filter(|&code| code != missing) {
+            let mut rows = vec![0; 20];
+            rows[10] = 0b0000110000;
+            unicode.glyph_add(code, rows);
+        }
+        for (code, rows) in glyphs {
+            unicode.glyph_add(*code, rows.clone());
+        }
+        let control = font(7, 14, 0x20..0x7F, 2);
+        let hex = font(7, 13, 0x20..0x7F, 3);
+        Rom::from_fonts(&Fonts::default(), unicode, &control, hex, None).unwrap()
+    }
+
+    #[test]
+    fn missing_ascii() {
+        let issues = check(&rom(&[], 0x5A));
+        assert_eq!(issues.len(), 1);
+        assert!(matches!(issues[0], Issue::MissingAscii { code: 0x5A, .. }));
+        assert!(issues[0].is_error());
+        assert_eq!(issues[0].to_string(), "test: missing printable ASCII 0x5A 'Z'");
+    }
+
+    #[test]
+    fn edges() {
+        let mut top = vec![0; 20];
+        top[0] = 0b0000100000;
+        let mut bottom = vec![0; 20];
+        bottom[19] = 0b0000100000;
+        let right = vec![0b0000000001; 20];
+        let left = vec![0b1000000000; 20];
+        let issues = check(&rom(&[(0x41, top), (0x42, bottom), (0x43, right), (0x44, left)], 0));
+        let found: Vec<String> = issues.iter().map(|issue| issue.to_string()).collect();
+        assert_eq!(found, [
+            "ascii: 0x41 U+0041 touches the top edge",
+            "ascii: 0x42 U+0042 touches the bottom edge",
+            "ascii: 0x43 U+0043 touches the top, bottom, right edge",
+            "ascii: 0x44 U+0044 touches the top, bottom edge",
+            "ascii: 0x44 U+0044 uses the leftmost column, bit 9",
+        ]);
+        assert!(issues.iter().all(|issue| !issue.is_error()));
+    }
+}
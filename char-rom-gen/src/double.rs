@@ -0,0 +1,102 @@
+//! Double width and double height line glyphs, VT100 `ESC # 3/4/6`.
+//!
+//! A double width line shows each glyph scaled 2x horizontally over two
+//! cells, a double height line 2x in both directions over two cells and two
+//! lines. Every base glyph therefore gives six half glyphs, each the size of
+//! a cell.
+
+use crate::bdf::{
+    Font,
+    Glyph,
+};
+
+/// The glyph shapes of one bank, in ROM order. The `char_rom` line attribute
+/// and half select give the index `line == 0 ? 0 : {line, half} - 1`.
+pub const SHAPES: [(&str, Shape); 7] = [
+    ("single", Shape::Single),
+    ("double width left", Shape::Width { right: false }),
+    ("double width right", Shape::Width { right: true }),
+    ("double height top left", Shape::Height { bottom: false, right: false }),
+    ("double height top right", Shape::Height { bottom: false, right: true }),
+    ("double height bottom left", Shape::Height { bottom: true, right: false }),
+    ("double height bottom right", Shape::Height { bottom: true, right: true }),
+];
+
+#[derive(Clone, Copy)]
+pub enum Shape {
+    /// The glyph as it is
+    Single,
+    /// A half of the glyph doubled horizontally, DECDWL
+    Width { right: bool },
+    /// A quarter of the glyph doubled in both directions, DECDHL
+    Height { bottom: bool, right: bool },
+}
+
+/// Widest cell that can be doubled.
+pub const MAX_WIDTH: usize = usize::BITS as usize / 2;
+
+/// The glyphs of `font` in `shape`, optionally smoothing the diagonals of
+/// the double height glyphs. The cell must be at most [`MAX_WIDTH`] wide.
+/// Codes `0..256` the font lacks get the shaped default or blank glyph.
+pub fn shape(font: &Font, shape: Shape, smooth: bool) -> Font {
+    let mut shaped = font.clone();
+    if matches!(shape, Shape::Single) {
+        return shaped;
+    }
+    for code in 0..256 {
+        shaped.glyphs.entry(code)
+            .or_insert_with(|| font.glyph_or_default(code).clone());
+    }
+    for glyph in shaped.glyphs.values_mut() {
+        let (width, height) = (glyph.width, glyph.height);
+        let half = match shape {
+            Shape::Single => continue,
+            Shape::Width { right } => glyph.scale(2, 1)
+                .crop(if right { width } else { 0 }, 0, width, height),
+            Shape::Height { bottom, right } => {
+                let scaled = if smooth { scale2x(glyph) } else { glyph.scale(2, 2) };
+                scaled.crop(if right { width } else { 0 }, if bottom { height } else { 0 }, width, height)
+            }
+        };
+        glyph.rows = half.rows;
+    }
+    shaped
+}
+
+/// Scale2x (EPX), 2x scaling that rounds off the staircase of diagonals.
+fn scale2x(glyph: &Glyph) -> Glyph {
+    let mut scaled = glyph.scale(2, 2);
+    let at = |x: isize, y: isize| x >= 0 && y >= 0 && glyph.get(x as usize, y as usize);
+    for y in 0..glyph.height as isize {
+        for x in 0..glyph.width as isize {
+            let e = at(x, y);
+            let (b, d, f, h) = (at(x, y - 1), at(x - 1, y), at(x + 1, y), at(x, y + 1));
+            let (x, y) = (x as usize * 2, y as usize * 2);
+            scaled.set(x, y, if d == b && b != f && d != h { d } else { e });
+            scaled.set(x + 1, y, if b == f && b != d && f != h { f } else { e });
+            scaled.set(x, y + 1, if d == h && d != b && h != f { d } else { e });
+            scaled.set(x + 1, y + 1, if h == f && d != h && b != f { f } else { e });
+        }
+    }
+    scaled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bdf::Property;
+
+    #[test]
+    fn default_glyphs() {
+        let mut font = Font::new("", 4, 2, 2).unwrap();
+        font.glyph_add(0x41, vec![0b1100, 0b0011]);
+        font.properties.insert(String::from("DEFAULT_CHAR"), Property::Integer(0x41));
+        let left = shape(&font, Shape::Width { right: false }, false);
+        assert_eq!(left.glyph(0x41).unwrap().rows, vec![0b1111, 0b0000]);
+        // a code the font lacks shows the doubled DEFAULT_CHAR, not the
+        // single size one
+        assert_eq!(left.glyph(0x42).unwrap().rows, vec![0b1111, 0b0000]);
+        let right = shape(&font, Shape::Width { right: true }, false);
+        assert_eq!(right.glyph(0x42).unwrap().rows, vec![0b0000, 0b1111]);
+    }
+}
@@ -0,0 +1,116 @@
+//! Grayscale images written as PNG or PBM, or as indexed colour PNG.
+
+use anyhow::{
+	Context,
+	Result,
+};
+use std::path::Path;
+
+pub const BLACK: u8 = 0x00;
+pub const WHITE: u8 = 0xFF;
+
+/// 8 bit grayscale image, row major.
+pub struct Image {
+	pub width: usize,
+	pub height: usize,
+	pub pixels: Vec<u8>,
+}
+
+impl Image {
+	pub fn new(width: usize, height: usize, fill: u8) -> Self {
+		Image {
+			width,
+			height,
+			pixels: vec![fill; width * height],
+		}
+	}
+
+	pub fn set(&mut self, x: usize, y: usize, value: u8) {
+		if x < self.width && y < self.height {
+			self.pixels[(y * self.width) + x] = value;
+		}
+	}
+
+	pub fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, value: u8) {
+		for y in y..(y + height) {
+			for x in x..(x + width) {
+				self.set(x, y, value);
+			}
+		}
+	}
+
+	/// Draw `rows` of `width` bits, most significant bit on the left, only
+	/// the set pixels are drawn.
+	pub fn draw(&mut self, x: usize, y: usize, width: usize, rows: &[usize], value: u8) {
+		for (i, row) in rows.iter().enumerate() {
+			for j in 0..width {
+				if (row >> (width - 1 - j)) & 1 == 1 {
+					self.set(x + j, y + i, value);
+				}
+			}
+		}
+	}
+
+	/// Nearest neighbour scaling by a whole `factor`.
+	pub fn scale(&self, factor: usize) -> Image {
+		let mut image = Image::new(self.width * factor, self.height * factor, WHITE);
+		for y in 0..image.height {
+			for x in 0..image.width {
+				image.pixels[(y * image.width) + x] =
+					self.pixels[((y / factor) * self.width) + (x / factor)];
+			}
+		}
+		image
+	}
+
+	pub fn png(&self) -> Result<Vec<u8>> {
+		let mut bytes = Vec::new();
+		let mut encoder = png::Encoder::new(&mut bytes, self.width as u32, self.height as u32);
+		encoder.set_color(png::ColorType::Grayscale);
+		encoder.set_depth(png::BitDepth::Eight);
+		let mut writer = encoder.write_header().context("PNG header")?;
+		writer.write_image_data(&self.pixels).context("PNG data")?;
+		writer.finish().context("PNG")?;
+		Ok(bytes)
+	}
+
+	/// Indexed colour PNG, the pixels being indices into `palette`.
+	pub fn png_indexed(&self, palette: &[[u8; 3]]) -> Result<Vec<u8>> {
+		let mut bytes = Vec::new();
+		let mut encoder = png::Encoder::new(&mut bytes, self.width as u32, self.height as u32);
+		encoder.set_color(png::ColorType::Indexed);
+		encoder.set_depth(png::BitDepth::Eight);
+		encoder.set_palette(palette.concat());
+		let mut writer = encoder.write_header().context("PNG header")?;
+		writer.write_image_data(&self.pixels).context("PNG data")?;
+		writer.finish().context("PNG")?;
+		Ok(bytes)
+	}
+
+	/// Binary (P4) portable bitmap, anything darker than mid gray is black.
+	pub fn pbm(&self) -> Vec<u8> {
+		let mut bytes = format!("P4\n{} {}\n", self.width, self.height).into_bytes();
+		for row in self.pixels.chunks_exact(self.width) {
+			for pixels in row.chunks(8) {
+				let mut byte = 0u8;
+				for (i, &pixel) in pixels.iter().enumerate() {
+					if pixel < 0x80 {
+						byte |= 0x80 >> i;
+					}
+				}
+				bytes.push(byte);
+			}
+		}
+		bytes
+	}
+
+	/// PBM for a `.pbm` extension, otherwise PNG.
+	pub fn save(&self, path: &Path) -> Result<()> {
+		let bytes = match path.extension().and_then(|extension| extension.to_str()) {
+			Some("pbm") => self.pbm(),
+			_ => self.png()?,
+		};
+		std::fs::write(path, bytes)
+			.with_context(|| format!("Could not write {}", path.display()))
+	}
+}
@@ -0,0 +1,11 @@
+pub mod bdf;
+pub mod charset;
+pub mod coverage;
+pub mod double;
+pub mod image;
+pub mod model;
+pub mod overrides;
+pub mod preview;
+pub mod psf;
+pub mod rom;
+pub mod source;
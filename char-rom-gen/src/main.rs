@@ -1,252 +1,276 @@
-use anyhow::Context;
+use anyhow::{
+    Context,
+    Result,
+    bail,
+};
+use clap::Parser;
+use gowin_bsram::memory::Style;
+use std::path::PathBuf;
 
-mod bdf;
+use char_rom_gen::charset::Charset;
+use char_rom_gen::coverage;
+use char_rom_gen::double;
+use char_rom_gen::overrides;
+use char_rom_gen::psf;
+use char_rom_gen::rom::{
+    Fonts,
+    Rom,
+};
+use char_rom_gen::source::{
+    self,
+    Cache,
+    Source,
+};
 
-fn main() -> anyhow::Result<()> {
-    let bdf = reqwest::blocking::get("https://github.com/sunaku/tamzen-font/raw/refs/heads/master/bdf/Tamzen10x20r.bdf")?
-        .text()?;
-    let mut font = bdf::parse(&bdf).context("Tamzen10x20r.bdf")?;
+/// Generate the Verilog character ROM from BDF fonts.
+///
+/// Fonts are either a name in the vendored font cache or a path to a local
+/// BDF or PSF file.
+#[derive(Parser)]
+struct Args {
+    /// Primary font, its cell size sets the geometry of the ROM
+    #[arg(long, default_value = "Tamzen10x20r.bdf")]
+    font: Source,
 
-    assert_eq!(font.width, 10);
-    assert_eq!(font.height, 20);
-    for (_code_point, glyph) in &font.glyphs {
-        assert_eq!(glyph.width, font.width);
-        assert_eq!(glyph.height, font.height);
-    }
+    /// Font providing the small capital letters of "^C" style control glyphs,
+    /// including "^?" for DEL
+    #[arg(long, default_value = "Tamzen7x14r.bdf")]
+    control_font: Source,
+
+    /// Font providing the digits of the diagonal hex glyphs for 128..255
+    #[arg(long, default_value = "Tamzen7x13r.bdf")]
+    hex_font: Source,
+
+    /// Glyphs drawn as `#`/`.` ASCII art by code point, replacing those of
+    /// the fonts
+    #[arg(long)]
+    overrides: Option<PathBuf>,
+
+    /// Add a bold bank for each charset and the bold, underline and reverse
+    /// attribute inputs to the ROM
+    #[arg(long)]
+    attributes: bool,
+
+    /// Bold font for --attributes, the same cell size as the primary font
+    #[arg(long, default_value = "Tamzen10x20b.bdf")]
+    bold_font: Source,
+
+    /// Charset of each ROM bank, a built in name (ascii, uk, cp437, latin-1,
+    /// dec-special-graphics) or a mapping table file; more than one adds a
+    /// bank select input to the ROM
+    #[arg(long = "charset", default_value = "ascii")]
+    charsets: Vec<String>,
+
+    /// Mapping table of the upper half 0x80..0xFF for all banks, a built in
+    /// name (cp437, latin-1) or a file; unmapped codes keep the hex glyphs
+    #[arg(long)]
+    upper: Option<String>,
+
+    /// Add double width and double height half glyphs, selected by the ROM
+    /// line attribute and half inputs
+    #[arg(long)]
+    double: bool,
+
+    /// Smooth the diagonals of double height glyphs
+    #[arg(long, requires = "double")]
+    smooth: bool,
+
+    /// Region exported by --bdf, --psf and --preview, counting the banks
+    /// or with --double the shapes of each bank as listed in the Verilog
+    #[arg(long, default_value_t = 0)]
+    region: usize,
+
+    /// Directory of vendored fonts and their SHA256SUMS
+    #[arg(long, default_value = source::FONTS)]
+    fonts: PathBuf,
+
+    /// Read the primary, control, hex and bold fonts from the cache,
+    /// downloading missing ones when built with the `fetch` feature, then
+    /// exit
+    #[arg(long)]
+    fetch_fonts: bool,
 
-    // construct "^C" style control codes
-    let bdf = reqwest::blocking::get("https://github.com/sunaku/tamzen-font/raw/refs/heads/master/bdf/Tamzen7x14r.bdf")?
-        .text()?;
-    let small = bdf::parse(&bdf).context("Tamzen7x14r.bdf")?;
-    for index in 1..32 {
-        // start with the "^" hat
-        let mut rows = vec![
-            0, 0, 0,
-            128, 256+64, 512+32,
-            0, 0, 0, 0,
-            0, 0, 0, 0,
-            0, 0, 0,
-            0, 0, 0,
-        ];
-        // as the small capital letter
-        let small = small.glyph(0x40 + index).expect("A..Z");
-        for (i, row) in small.rows.iter().enumerate() {
-            rows[i + 3] ^= row;
+    /// Verilog file to generate
+    #[arg(long, short, default_value = concat!(env!("CARGO_MANIFEST_DIR"), "/../fpga/src/char_rom.sv"))]
+    output: PathBuf,
+
+    /// Generate a behavioural ROM loading `$readmemh` instead of the Gowin
+    /// primitives, writing its `.hex` and a Gowin `.mi` file beside the
+    /// Verilog, for simulation
+    #[arg(long)]
+    readmemh: bool,
+
+    /// Also write the final 256 glyph font as a BDF file
+    #[arg(long)]
+    bdf: Option<PathBuf>,
+
+    /// Also write the final 256 glyph font as a console font, PSF1 for a
+    /// `.psf1` extension (8 pixel wide fonts only) otherwise PSF2
+    #[arg(long)]
+    psf: Option<PathBuf>,
+
+    /// Also render the final 256 glyphs as a 16x16 glyph sheet, a PBM for a
+    /// `.pbm` extension otherwise a PNG
+    #[arg(long)]
+    preview: Option<PathBuf>,
+
+    /// Scale factor of the glyph sheet
+    #[arg(long, default_value_t = 2)]
+    preview_scale: usize,
+
+    /// Print the BSRAM layout and utilization of the ROM
+    #[arg(long)]
+    report: bool,
+
+    /// Report missing printable ASCII and charset glyphs, and glyphs touching
+    /// the cell edges or using the leftmost column, instead of writing the
+    /// Verilog; fails on missing glyphs
+    #[arg(long)]
+    coverage: bool,
+
+    /// Compare against the existing output file, with --readmemh also its
+    /// `.hex` and `.mi` files, instead of writing them
+    #[arg(long)]
+    check: bool,
+
+    /// Decode the glyphs of the existing output file from its block INIT
+    /// parameters and address mapping and compare them with the fonts,
+    /// instead of writing it
+    #[arg(long)]
+    verify: bool,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let cache = Cache::new(&args.fonts);
+    if args.fetch_fonts {
+        for source in [&args.font, &args.control_font, &args.hex_font, &args.bold_font] {
+            source.read(&cache).with_context(|| format!("Font {source}"))?;
+            println!("{source}");
+        }
+        return Ok(());
+    }
+    let fonts = Fonts {
+        font: args.font,
+        control: args.control_font,
+        hex: args.hex_font,
+        bold: args.attributes.then_some(args.bold_font),
+    };
+    let mut rom = Rom::load(&cache, &fonts)?;
+    if let Some(path) = &args.overrides {
+        let overrides = overrides::load(path, rom.font.width, rom.font.height)?;
+        rom.add_overrides(&path.display().to_string(), &overrides);
+    }
+    rom.charsets = args.charsets.iter()
+        .map(|name| Charset::load(name))
+        .collect::<Result<_>>()?;
+    if args.double && rom.font.width > double::MAX_WIDTH {
+        bail!("--double needs a font at most {} pixels wide", double::MAX_WIDTH);
+    }
+    rom.double = args.double;
+    rom.smooth = args.smooth;
+    if let Some(name) = &args.upper {
+        let upper = Charset::load(name)?;
+        if !upper.is_upper() {
+            bail!("Charset {name} maps codes below 0x80, use it with --charset");
         }
-        font.glyph_add(index, rows);
+        rom.upper = Some(upper);
     }
 
-    // construct diagonal two digit hex glpyhs for non-ASCII
-    let bdf = reqwest::blocking::get("https://github.com/sunaku/tamzen-font/raw/refs/heads/master/bdf/Tamzen7x13r.bdf")?
-        .text()?;
-    let small = bdf::parse(&bdf).context("Tamzen7x13r.bdf")?;
-    for index in 127..256 {
-        // start with empty glyph
-        let mut rows = vec![
-            0, 0, 0, 0,
-            0, 0, 0, 0,
-            0, 0, 0, 0,
-            0, 0, 0, 0,
-            0, 0, 0, 0,
-        ];
-        // top-left digit
-        let digit = (index >> 4) & 15;
-        let digit = char::from_digit(digit, 16).expect("hex")
-            .to_ascii_uppercase() as usize;
-        let digit = small.glyph(digit).expect("0..9 A..F");
-        for (i, row) in digit.rows.iter().enumerate() {
-            if i > 0 {
-                rows[i - 1] ^= row << 3;
-            }
+    let regions = rom.regions();
+    let Some((_, region)) = regions.get(args.region) else {
+        bail!("No region {}, the ROM has {} region(s)", args.region, regions.len());
+    };
+    let code_points = rom.code_points(args.region);
+    let unicode = |code| code_points.get(&code).copied();
+    if let Some(path) = &args.bdf {
+        std::fs::write(path, region.write(unicode))
+            .with_context(|| format!("Could not write {}", path.display()))?;
+    }
+    if let Some(path) = &args.psf {
+        let bytes = if path.extension().is_some_and(|extension| extension == "psf1") {
+            psf::write_psf1(region, unicode)?
+        } else {
+            psf::write_psf2(region, unicode)
+        };
+        std::fs::write(path, bytes)
+            .with_context(|| format!("Could not write {}", path.display()))?;
+    }
+
+    if let Some(path) = &args.preview {
+        rom.preview(region)
+            .scale(args.preview_scale.max(1))
+            .save(path)?;
+    }
+
+    if args.report {
+        print!("{}", rom.layout()?.report());
+    }
+
+    if args.coverage {
+        let issues = coverage::check(&rom);
+        for issue in &issues {
+            let severity = if issue.is_error() { "error" } else { "warning" };
+            println!("{severity}: {issue}");
         }
-        // bottom-right digit
-        let digit = index & 15;
-        let digit = char::from_digit(digit, 16).expect("hex")
-            .to_ascii_uppercase() as usize;
-        let digit = small.glyph(digit).expect("0..9 A..F");
-        for (i, row) in digit.rows.iter().enumerate() {
-            rows[i + 7] ^= row;
+        let errors = issues.iter().filter(|issue| issue.is_error()).count();
+        println!("{errors} missing glyph(s), {} warning(s)", issues.len() - errors);
+        if errors > 0 {
+            bail!("{errors} missing glyph(s)");
         }
-        // add glyph
-        font.glyph_add(index as usize, rows);
+        return Ok(());
     }
 
-    let mut verilog = String::with_capacity(30000);
-    char_rom(&font, &mut verilog);
-    std::fs::write("../fpga/src/char_rom.sv", &verilog)?;
+    if args.verify {
+        let verilog = std::fs::read_to_string(&args.output)
+            .with_context(|| format!("Could not read {}", args.output.display()))?;
+        rom.verify(&verilog)
+            .with_context(|| format!("{} does not match the fonts", args.output.display()))?;
+        println!("{} matches the fonts", args.output.display());
+        return Ok(());
+    }
 
-    Ok(())
-}
+    // the Verilog and with --readmemh its hex and .mi files
+    let mut outputs = Vec::new();
+    if args.readmemh {
+        let hex = args.output.with_extension("hex");
+        let file = hex.file_name().expect("output file").to_string_lossy().into_owned();
+        rom.style = Style::Readmemh { file };
+        let memory = rom.memory()?;
+        outputs.push((hex, memory.hex()));
+        outputs.push((args.output.with_extension("mi"), memory.mi()));
+    }
+    outputs.insert(0, (args.output.clone(), rom.verilog()?));
 
-fn char_rom(font: &bdf::Font, verilog: &mut String) {
-    verilog.push_str("`default_nettype none\n");
-    verilog.push_str("`timescale 1ns / 1ps\n");
-    verilog.push_str("module char_rom\n");
-    verilog.push_str("(\n");
-    verilog.push_str("    input wire clk,\n");
-    verilog.push_str("    input wire ce,\n");
-    verilog.push_str("    input wire [7:0] char,\n");
-    verilog.push_str("    input wire [4:0] row,\n");
-    verilog.push_str("    output wire [9:0] q\n");
-    verilog.push_str(");\n");
-    verilog.push_str("\n");
-    verilog.push_str("    reg [1:0] block;\n");
-    verilog.push_str("\n");
-    verilog.push_str("    always @(posedge clk) begin\n");
-    verilog.push_str("        if (ce) begin\n");
-    verilog.push_str("            block <= row[3:2];\n");
-    verilog.push_str("        end\n");
-    verilog.push_str("    end\n");
-    verilog.push_str("\n");
-    verilog.push_str("    wire [31:0] block_0_q;\n");
-    verilog.push_str("    wire [31:0] block_1_q;\n");
-    verilog.push_str("    wire [31:0] block_2_q;\n");
-    verilog.push_str("    wire [31:0] block_3_q;\n");
-    verilog.push_str("\n");
-    verilog.push_str("    pROM\n");
-    verilog.push_str("    #(\n");
-    verilog.push_str("        .READ_MODE(1'b0),\n");
-    verilog.push_str("        .BIT_WIDTH(8),\n");
-    verilog.push_str("        .RESET_MODE(\"SYNC\"");
-    for row in 0..8 {
-        for upper in 0..8 {
-            verilog.push_str("),\n");
-            let init = (row * 8) + upper;
-            let mut line = format!("        .INIT_RAM_{init:02X}(256'h");
-            line.reserve(110);
-            for lower in (0..32).rev() {
-                let glyph = (upper * 32) + lower;
-                let glyph = font.glyph_or_blank(glyph);
-                let row = glyph.rows[row];
-                line.push(char::from_digit(((row >> 4) & 15) as u32, 16).expect("hex"));
-                line.push(char::from_digit(((row >> 0) & 15) as u32, 16).expect("hex"));
+    if args.check {
+        let mut outdated = 0;
+        for (path, contents) in &outputs {
+            if !check(path, contents)? {
+                outdated += 1;
             }
-            verilog.push_str(&line);
         }
-    }
-    verilog.push_str(")\n");
-    verilog.push_str("    )\n");
-    verilog.push_str("    block_0\n");
-    verilog.push_str("    (\n");
-    verilog.push_str("        .DO(block_0_q),\n");
-    verilog.push_str("        .CLK(clk),\n");
-    verilog.push_str("        .OCE(1'b1),\n");
-    verilog.push_str("        .CE(ce & ~row[4] & ~row[3]),\n");
-    verilog.push_str("        .RESET(1'b0),\n");
-    verilog.push_str("        .AD({row[2:0],char[7:0],3'b000})\n");
-    verilog.push_str("    );\n");
-    verilog.push_str("\n");
-    verilog.push_str("    pROM\n");
-    verilog.push_str("    #(\n");
-    verilog.push_str("        .READ_MODE(1'b0),\n");
-    verilog.push_str("        .BIT_WIDTH(8),\n");
-    verilog.push_str("        .RESET_MODE(\"SYNC\"");
-    for row in 8..16 {
-        for upper in 0..8 {
-            verilog.push_str("),\n");
-            let init = ((row * 8) + upper) & 63;
-            let mut line = format!("        .INIT_RAM_{init:02X}(256'h");
-            line.reserve(110);
-            for lower in (0..32).rev() {
-                let glyph = (upper * 32) + lower;
-                let glyph = font.glyph_or_blank(glyph);
-                let row = glyph.rows[row];
-                line.push(char::from_digit(((row >> 4) & 15) as u32, 16).expect("hex"));
-                line.push(char::from_digit(((row >> 0) & 15) as u32, 16).expect("hex"));
-            }
-            verilog.push_str(&line);
+        if outdated > 0 {
+            bail!("{outdated} file(s) out of date");
         }
+        return Ok(());
     }
-    verilog.push_str(")\n");
-    verilog.push_str("    )\n");
-    verilog.push_str("    block_1\n");
-    verilog.push_str("    (\n");
-    verilog.push_str("        .DO(block_1_q),\n");
-    verilog.push_str("        .CLK(clk),\n");
-    verilog.push_str("        .OCE(1'b1),\n");
-    verilog.push_str("        .CE(ce & ~row[4] & row[3]),\n");
-    verilog.push_str("        .RESET(1'b0),\n");
-    verilog.push_str("        .AD({row[2:0],char[7:0],3'b000})\n");
-    verilog.push_str("    );\n");
-    verilog.push_str("\n");
-    verilog.push_str("    pROM\n");
-    verilog.push_str("    #(\n");
-    verilog.push_str("        .READ_MODE(1'b0),\n");
-    verilog.push_str("        .BIT_WIDTH(8),\n");
-    verilog.push_str("        .RESET_MODE(\"SYNC\"");
-    for row in 16..20 {
-        for upper in 0..8 {
-            verilog.push_str("),\n");
-            let init = ((row * 8) + upper) & 63;
-            let mut line = format!("        .INIT_RAM_{init:02X}(256'h");
-            line.reserve(110);
-            for lower in (0..32).rev() {
-                let glyph = (upper * 32) + lower;
-                let glyph = font.glyph_or_blank(glyph);
-                let row = glyph.rows[row];
-                line.push(char::from_digit(((row >> 4) & 15) as u32, 16).expect("hex"));
-                line.push(char::from_digit(((row >> 0) & 15) as u32, 16).expect("hex"));
-            }
-            verilog.push_str(&line);
-        }
+    for (path, contents) in &outputs {
+        std::fs::write(path, contents)
+            .with_context(|| format!("Could not write {}", path.display()))?;
     }
-    verilog.push_str(")\n");
-    verilog.push_str("    )\n");
-    verilog.push_str("    block_2\n");
-    verilog.push_str("    (\n");
-    verilog.push_str("        .DO(block_2_q),\n");
-    verilog.push_str("        .CLK(clk),\n");
-    verilog.push_str("        .OCE(1'b1),\n");
-    verilog.push_str("        .CE(ce & row[4] & ~row[3]),\n");
-    verilog.push_str("        .RESET(1'b0),\n");
-    verilog.push_str("        .AD({row[2:0],char[7:0],3'b000})\n");
-    verilog.push_str("    );\n");
-    verilog.push_str("\n");
-    verilog.push_str("    pROM\n");
-    verilog.push_str("    #(\n");
-    verilog.push_str("        .READ_MODE(1'b0),\n");
-    verilog.push_str("        .BIT_WIDTH(2),\n");
-    verilog.push_str("        .RESET_MODE(\"SYNC\"");
-    for row in 0..20 {
-        for upper in 0..2 {
-            verilog.push_str("),\n");
-            let init = (row * 2) + upper;
-            let mut line = format!("        .INIT_RAM_{init:02X}(256'h");
-            line.reserve(110);
-            let mut digit = 0u32;
-            for lower in (0..128).rev() {
-                let glyph = (upper * 128) + lower;
-                let glyph = font.glyph_or_blank(glyph);
-                let row = glyph.rows[row];
-                if lower % 2 == 1 {
-                    digit = ((row >> 6) & 12) as u32;
-                } else {
-                    digit |= ((row >> 8) & 3) as u32;
-                    line.push(char::from_digit(digit, 16).expect("hex"));
-                }
-            }
-            verilog.push_str(&line);
-        }
+    Ok(())
+}
+
+/// Whether `path` holds `contents`, printing a diff otherwise.
+fn check(path: &std::path::Path, contents: &str) -> Result<bool> {
+    let existing = std::fs::read_to_string(path)
+        .with_context(|| format!("Could not read {}", path.display()))?;
+    if existing == contents {
+        println!("{} is up to date", path.display());
+        return Ok(true);
     }
-    verilog.push_str(")\n");
-    verilog.push_str("    )\n");
-    verilog.push_str("    block_3\n");
-    verilog.push_str("    (\n");
-    verilog.push_str("        .DO(block_3_q),\n");
-    verilog.push_str("        .CLK(clk),\n");
-    verilog.push_str("        .OCE(1'b1),\n");
-    verilog.push_str("        .CE(ce),\n");
-    verilog.push_str("        .RESET(1'b0),\n");
-    verilog.push_str("        .AD({row[4:0],char[7:0],1'b0})\n");
-    verilog.push_str("    );\n");
-    verilog.push_str("\n");
-    verilog.push_str("    assign q = row[4]\n");
-    verilog.push_str("        ? (row[3]\n");
-    verilog.push_str("            ? 10'b0000000000\n");
-    verilog.push_str("            : {block_3_q[1:0], block_2_q[7:0]})\n");
-    verilog.push_str("        : (row[3]\n");
-    verilog.push_str("            ? {block_3_q[1:0], block_1_q[7:0]}\n");
-    verilog.push_str("            : {block_3_q[1:0], block_0_q[7:0]});\n");
-    verilog.push_str("\n");
-    verilog.push_str("endmodule\n");
+    let diff = similar::TextDiff::from_lines(existing.as_str(), contents);
+    print!("{}", diff.unified_diff().header(&path.display().to_string(), "generated"));
+    println!("{} is out of date", path.display());
+    Ok(false)
 }
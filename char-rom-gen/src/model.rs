@@ -0,0 +1,271 @@
+//! Software model of the generated `char_rom` module.
+//!
+//! The content is decoded from the generated Verilog, so lookups give the
+//! `q` the hardware shows one clock after the inputs, including the block
+//! select of the address and the rows past the glyph height, which read as
+//! zero.
+
+use anyhow::{
+    Context,
+    Result,
+    bail,
+};
+use gowin_bsram::decode;
+use gowin_bsram::memory::Style;
+
+use crate::double;
+use crate::rom::{
+    self,
+    Rom,
+};
+
+/// Inputs of `char_rom`, those the ROM was generated without are ignored.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Inputs {
+    pub bank: usize,
+    pub bold: bool,
+    pub underline: bool,
+    pub reverse: bool,
+    /// 0 single, 1 double width, 2 double height top, 3 bottom
+    pub line: usize,
+    /// Right half of a double width or height glyph
+    pub half: bool,
+    pub char: u8,
+    pub row: usize,
+}
+
+pub struct CharRom {
+    width: usize,
+    height: usize,
+    /// Width of the `row` input
+    row_width: usize,
+    /// Width of `glyph_row`, `addr` being `{glyph_row, char}`
+    glyph_row_width: usize,
+    banks: usize,
+    /// Row of the underline, with the attribute inputs
+    underline_row: Option<usize>,
+    double: bool,
+    /// `q` of the blocks by address
+    content: Vec<u64>,
+}
+
+impl CharRom {
+    /// Model of the `char_rom` generated for `rom`.
+    pub fn new(rom: &Rom) -> Result<Self> {
+        match &rom.style {
+            Style::Gowin => Self::decode(&rom.verilog()?),
+            // the behavioural ROM reads the memory content as it is
+            Style::Readmemh { .. } | Style::ReadmemhParameter { .. } => {
+                let mut model = Self::geometry(rom);
+                let memory = rom.memory()?;
+                model.content = (0..(1 << (model.glyph_row_width + 8)))
+                    .map(|address| memory.get(address))
+                    .collect();
+                Ok(model)
+            }
+        }
+    }
+
+    /// Model of an existing `char_rom.sv`, decoded from its block INIT
+    /// parameters. The geometry is read from the ports and the header
+    /// comments of the module, so that no fonts are needed.
+    pub fn decode(verilog: &str) -> Result<Self> {
+        let Some(width) = port(verilog, "q") else {
+            bail!("No q output");
+        };
+        let Some(row_width) = port(verilog, "row") else {
+            bail!("No row input");
+        };
+        // the cell size is only given when rows past the height exist
+        let cells = format!("// {width}x");
+        let height = match verilog.lines().find_map(|line| line.strip_prefix(&cells)) {
+            Some(rest) => {
+                let height = rest.split_once(" cells").map_or(rest, |(height, _)| height);
+                height.parse().with_context(|| format!("Cell height '{height}'"))?
+            }
+            None => 1 << row_width,
+        };
+        let underline_row = match verilog.lines().find_map(|line| line.strip_prefix("// underline row ")) {
+            Some(row) => Some(row.parse().with_context(|| format!("Underline row '{row}'"))?),
+            None => None,
+        };
+        let double = port(verilog, "line").is_some();
+        let regions = verilog.lines()
+            .filter(|line| line.starts_with("// region "))
+            .count()
+            .max(1);
+        let shapes = if double { double::SHAPES.len() } else { 1 };
+        let weights = if underline_row.is_some() { 2 } else { 1 };
+        let glyph_row_width = rom::address_width(regions * height);
+        let q = rom::glyph_output(underline_row.is_some(), rom::gated(regions, height));
+        Ok(CharRom {
+            width,
+            height,
+            row_width,
+            glyph_row_width,
+            banks: regions / (shapes * weights),
+            underline_row,
+            double,
+            content: decode::decode(verilog, "block", "addr", q, glyph_row_width + 8)?,
+        })
+    }
+
+    /// The model without content.
+    fn geometry(rom: &Rom) -> Self {
+        let regions = rom.regions().len();
+        CharRom {
+            width: rom.font.width,
+            height: rom.font.height,
+            row_width: rom::address_width(rom.font.height),
+            glyph_row_width: rom::address_width(regions * rom.font.height),
+            banks: rom.charsets.len(),
+            underline_row: rom.bold.is_some().then(|| rom.underline_row()),
+            double: rom.double,
+            content: Vec::new(),
+        }
+    }
+
+    /// Pixel width of the cells, the width of `q`.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Pixel height of the cells.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// `q` for `row` of `char` in the first bank, leftmost pixel in bit
+    /// `width - 1`.
+    pub fn q(&self, char: u8, row: usize) -> u64 {
+        self.read(&Inputs {
+            char,
+            row,
+            ..Inputs::default()
+        })
+    }
+
+    /// `q` for `inputs`, each truncated to the width of its port as in the
+    /// Verilog.
+    pub fn read(&self, inputs: &Inputs) -> u64 {
+        let row = inputs.row & mask(self.row_width);
+        // region = ((bank * weights) + bold) * shapes + shape
+        let mut region = 0;
+        if self.banks > 1 {
+            region = inputs.bank & mask(rom::address_width(self.banks));
+        }
+        if self.underline_row.is_some() {
+            region = (region * 2) + usize::from(inputs.bold);
+        }
+        if self.double {
+            let line = inputs.line & 3;
+            let shape = if line == 0 { 0 } else { ((line << 1) | usize::from(inputs.half)) - 1 };
+            region = (region * double::SHAPES.len()) + shape;
+        }
+        // glyph_row wraps around in its glyph_row_width bits
+        let glyph_row = ((region * self.height) + row) & mask(self.glyph_row_width);
+        // rows past the height are gated, or past the depth, reading zero
+        let glyph = if row < self.height {
+            self.content[(glyph_row << 8) | usize::from(inputs.char)]
+        } else {
+            0
+        };
+        match self.underline_row {
+            None => glyph,
+            Some(underline_row) => {
+                let all = u64::MAX >> (64 - self.width);
+                let q = if inputs.underline && row == underline_row { all } else { glyph };
+                if inputs.reverse { q ^ all } else { q }
+            }
+        }
+    }
+}
+
+/// Width of the input or output `name` of the module, `None` without it.
+fn port(verilog: &str, name: &str) -> Option<usize> {
+    verilog.lines().find_map(|line| {
+        let line = line.trim().trim_end_matches(',');
+        let declaration = line.strip_prefix("input wire ")
+            .or_else(|| line.strip_prefix("output wire "))?;
+        match declaration.strip_prefix('[') {
+            Some(range) => {
+                let (msb, port) = range.split_once(":0] ")?;
+                (port == name).then(|| msb.parse::<usize>().ok().map(|msb| msb + 1))?
+            }
+            None => (declaration == name).then_some(1),
+        }
+    })
+}
+
+/// The low `bits` bits set.
+fn mask(bits: usize) -> usize {
+    (1 << bits) - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::charset::Charset;
+    use crate::rom::tests::{
+        font,
+        rom,
+    };
+
+    #[test]
+    fn q() {
+        let (_, rom) = rom();
+        let model = CharRom::new(&rom).unwrap();
+        for char in 0..=255 {
+            let glyph = rom.font.glyph_or_default(usize::from(char));
+            for row in 0..20 {
+                assert_eq!(model.q(char, row), glyph.rows[row] as u64, "{char:#04X} row {row}");
+            }
+            for row in 20..32 {
+                assert_eq!(model.q(char, row), 0, "{char:#04X} row {row}");
+            }
+        }
+    }
+
+    #[test]
+    fn regions() {
+        let (_, mut rom) = rom();
+        rom.charsets = ["ascii", "dec-special-graphics"].iter()
+            .map(|name| Charset::load(name))
+            .collect::<Result<_>>()
+            .unwrap();
+        rom.bold = Some(font(10, 20, 0x20..0x7F, 4));
+        rom.double = true;
+        let model = CharRom::new(&rom).unwrap();
+        // the geometry read back from the generated Verilog
+        let geometry = CharRom::geometry(&rom);
+        assert_eq!((model.width, model.height, model.row_width, model.glyph_row_width),
+            (geometry.width, geometry.height, geometry.row_width, geometry.glyph_row_width));
+        assert_eq!((model.banks, model.underline_row, model.double),
+            (geometry.banks, geometry.underline_row, geometry.double));
+        let regions = rom.regions();
+        let cases = [
+            (Inputs { ..Inputs::default() }, "ascii single"),
+            (Inputs { bank: 1, ..Inputs::default() }, "dec-special-graphics single"),
+            (Inputs { bank: 1, bold: true, ..Inputs::default() }, "dec-special-graphics bold single"),
+            (Inputs { line: 1, half: true, ..Inputs::default() }, "ascii double width right"),
+            (Inputs { bold: true, line: 3, ..Inputs::default() }, "ascii bold double height bottom left"),
+        ];
+        for (inputs, name) in cases {
+            let (_, font) = regions.iter().find(|(region, _)| region == name).expect(name);
+            for char in 0..=255 {
+                for row in 0..32 {
+                    let expected = if row < 20 { font.glyph_or_default(usize::from(char)).rows[row] as u64 } else { 0 };
+                    assert_eq!(model.read(&Inputs { char, row, ..inputs }), expected, "{name} {char:#04X} row {row}");
+                }
+            }
+        }
+
+        // the attributes after the blocks
+        let all = (1 << 10) - 1;
+        let row = rom.underline_row();
+        let glyph = model.q(b'A', row);
+        assert_eq!(model.read(&Inputs { char: b'A', row, underline: true, ..Inputs::default() }), all);
+        assert_eq!(model.read(&Inputs { char: b'A', row, reverse: true, ..Inputs::default() }), glyph ^ all);
+        assert_eq!(model.read(&Inputs { char: b'A', row: 25, reverse: true, ..Inputs::default() }), all);
+    }
+}
@@ -0,0 +1,147 @@
+//! Glyph overrides drawn as ASCII art.
+//!
+//! Each glyph starts with its Unicode code point in hex, `0x09` or `U+2502`
+//! alike, optionally followed by a description, then one line per pixel row
+//! of `#` for a set pixel and `.` for a clear one. Lines starting with `# `
+//! are comments, blank lines are ignored.
+//!
+//! Glyphs are keyed by code point, not ROM code: an override shows wherever
+//! a bank maps a code to that code point, and below 0x80 also at the same
+//! code of the ASCII bank, C0 controls and DEL included.
+//!
+//! ```text
+//! # a visible tab marker
+//! 0x09 tab
+//! ..........
+//! ..#.......
+//! ```
+
+use anyhow::{
+    Context,
+    Result,
+    bail,
+};
+use std::path::Path;
+
+use crate::bdf::Glyph;
+use crate::charset;
+
+/// A glyph replacing the one of the font at `code_point`.
+pub struct Override {
+    pub code_point: usize,
+    pub glyph: Glyph,
+}
+
+/// Read an override file, each glyph validated against the `width` x
+/// `height` cell.
+pub fn load(path: &Path, width: usize, height: usize) -> Result<Vec<Override>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Could not read {}", path.display()))?;
+    parse(&text, width, height).with_context(|| format!("{}", path.display()))
+}
+
+pub fn parse(text: &str, width: usize, height: usize) -> Result<Vec<Override>> {
+    // the code point, description, first line and rows of each glyph
+    let mut drawn: Vec<(usize, String, usize, Vec<&str>)> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let number = number + 1;
+        let line = line.trim_end();
+        if line.is_empty() || line == "#" || line.starts_with("# ") {
+            continue;
+        }
+        if line.chars().all(|pixel| pixel == '#' || pixel == '.') {
+            let Some((_, _, _, rows)) = drawn.last_mut() else {
+                bail!("Line {number}: pixels before the first code point");
+            };
+            rows.push(line);
+            continue;
+        }
+        let (code_point, description) = line.split_once(char::is_whitespace)
+            .unwrap_or((line, ""));
+        let code_point = charset::hex(code_point)
+            .with_context(|| format!("Line {number}: expecting a code point or '#'/'.' pixels"))?;
+        if let Some((_, _, first, _)) = drawn.iter().find(|(other, ..)| *other == code_point) {
+            bail!("Line {number}: U+{code_point:04X} already drawn at line {first}");
+        }
+        drawn.push((code_point, description.trim().to_owned(), number, Vec::new()));
+    }
+
+    let mut overrides = Vec::new();
+    for (code_point, description, number, rows) in drawn {
+        let name = if description.is_empty() {
+            format!("U+{code_point:04X}")
+        } else {
+            description
+        };
+        let glyph = Glyph::from_art(&name, &rows)
+            .with_context(|| format!("Line {number}"))?;
+        if glyph.width != width || glyph.height != height {
+            bail!("Line {number}: {name} is {}x{}, the cell is {width}x{height}",
+                glyph.width, glyph.height);
+        }
+        overrides.push(Override {
+            code_point,
+            glyph,
+        });
+    }
+    Ok(overrides)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::charset::Charset;
+    use crate::rom::tests::rom;
+
+    #[test]
+    fn keys() {
+        let text = "# a comment\n#\n0x09 tab\n#..#\n.##.\n\nU+2502\n.#..\n.#..\n";
+        let overrides = parse(text, 4, 2).unwrap();
+        assert_eq!(overrides.len(), 2);
+        assert_eq!(overrides[0].code_point, 0x09);
+        assert_eq!(overrides[0].glyph.name, "tab");
+        assert_eq!(overrides[0].glyph.rows, vec![0b1001, 0b0110]);
+        assert_eq!(overrides[1].code_point, 0x2502);
+        assert_eq!(overrides[1].glyph.name, "U+2502");
+        assert_eq!(overrides[1].glyph.rows, vec![0b0100, 0b0100]);
+    }
+
+    #[test]
+    fn rejected() {
+        // a row short, a row too many, too narrow, uneven rows
+        for art in ["#..#\n", "#..#\n#..#\n#..#\n", "#..\n#..\n", "#..#\n#..\n"] {
+            let error = parse(&format!("0x41\n{art}"), 4, 2).err();
+            assert!(error.is_some(), "{art}");
+        }
+        assert!(parse("#..#\n", 4, 2).is_err());
+        assert!(parse("0x41\n#..#\n#..#\nU+0041\n#..#\n#..#\n", 4, 2).is_err());
+        assert!(parse("tab\n#..#\n#..#\n", 4, 2).is_err());
+    }
+
+    #[test]
+    fn ascii_bank() {
+        let (_, mut rom) = rom();
+        rom.charsets = ["ascii", "dec-special-graphics"].iter()
+            .map(|name| Charset::load(name))
+            .collect::<Result<_>>()
+            .unwrap();
+        let hex = rom.glyphs();
+        let art = |pixel: &str| format!("{}\n", pixel.repeat(10)).repeat(20);
+        let full = art("#");
+        let text = format!("0x09\n{full}0x41\n{full}U+00B0\n{full}U+2502\n{full}");
+        let overrides = parse(&text, 10, 20).unwrap();
+        rom.add_overrides("test", &overrides);
+        let all = vec![(1 << 10) - 1; 20];
+        let banks = rom.banks();
+        let (_, ascii) = &banks[0];
+        let (_, graphics) = &banks[1];
+        // below 0x80 also the ASCII bank, control codes included
+        assert_eq!(ascii.glyph(0x09).unwrap().rows, all);
+        assert_eq!(ascii.glyph(0x41).unwrap().rows, all);
+        // 0xB0 is a hex glyph, not U+00B0, without --upper
+        assert_eq!(ascii.glyph(0xB0).unwrap().rows, hex.glyph(0xB0).unwrap().rows);
+        // wherever a bank maps the code point
+        assert_eq!(graphics.glyph(0x78).unwrap().rows, all);
+        assert_eq!(ascii.glyph(0x78).unwrap().rows, hex.glyph(0x78).unwrap().rows);
+    }
+}
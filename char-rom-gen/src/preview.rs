@@ -0,0 +1,51 @@
+//! Glyph sheet of the character ROM, a 16x16 grid of cells labelled with
+//! their code point.
+
+use crate::bdf::Font;
+use crate::image::{
+	BLACK,
+	Image,
+	WHITE,
+};
+
+/// Grid lines between the tiles.
+const GRID: u8 = 0x60;
+/// Background of the character cell, showing its extent.
+const CELL: u8 = 0xE0;
+/// Code point labels, dark gray, black in a PBM.
+const LABEL: u8 = 0x40;
+const PADDING: usize = 2;
+
+/// Render code points `0..256` of `font`, labelled in two hex digits using
+/// glyphs `0..9 A..F` of `labels`.
+pub fn render(font: &Font, labels: &Font) -> Image {
+	let label_width = labels.width * 2;
+	let tile_width = font.width.max(label_width) + (PADDING * 2);
+	let tile_height = labels.height + font.height + (PADDING * 3);
+	let mut image = Image::new((tile_width * 16) + 1, (tile_height * 16) + 1, WHITE);
+	for index in 0..256 {
+		let x = (index & 15) * tile_width;
+		let y = (index >> 4) * tile_height;
+		// grid
+		image.fill(x, y, tile_width + 1, 1, GRID);
+		image.fill(x, y, 1, tile_height + 1, GRID);
+		image.fill(x, y + tile_height, tile_width + 1, 1, GRID);
+		image.fill(x + tile_width, y, 1, tile_height + 1, GRID);
+		// label
+		let label = format!("{index:02X}");
+		let mut label_x = x + ((tile_width - label_width) / 2) + 1;
+		for digit in label.chars() {
+			if let Some(glyph) = labels.glyph(digit as usize) {
+				image.draw(label_x, y + PADDING, labels.width, &glyph.rows, LABEL);
+			}
+			label_x += labels.width;
+		}
+		// glyph
+		let cell_x = x + ((tile_width - font.width) / 2) + 1;
+		let cell_y = y + labels.height + (PADDING * 2);
+		let glyph = font.glyph_or_default(index);
+		image.fill(cell_x, cell_y, font.width, font.height, CELL);
+		image.draw(cell_x, cell_y, font.width, &glyph.rows, BLACK);
+	}
+	image
+}
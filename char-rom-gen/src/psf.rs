@@ -0,0 +1,306 @@
+//! Linux console fonts, PSF1 and PSF2.
+//!
+//! PSF has no notion of a baseline, fonts are read with the whole cell above
+//! the baseline.
+
+use anyhow::{
+	bail,
+	Result,
+};
+use crate::bdf::{
+	Font,
+	Glyph,
+};
+
+const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+const PSF1_MODE_512: u8 = 0x01;
+const PSF1_MODE_HAS_TABLE: u8 = 0x02;
+const PSF1_SEPARATOR: u16 = 0xFFFF;
+const PSF1_START_SEQUENCE: u16 = 0xFFFE;
+
+const PSF2_MAGIC: [u8; 4] = [0x72, 0xB5, 0x4A, 0x86];
+const PSF2_HEADER_SIZE: usize = 32;
+const PSF2_HAS_UNICODE_TABLE: u32 = 0x01;
+const PSF2_SEPARATOR: u8 = 0xFF;
+const PSF2_START_SEQUENCE: u8 = 0xFE;
+
+pub fn is_psf(bytes: &[u8]) -> bool {
+	bytes.starts_with(&PSF1_MAGIC) || bytes.starts_with(&PSF2_MAGIC)
+}
+
+pub fn parse(bytes: &[u8]) -> Result<Font> {
+	if bytes.starts_with(&PSF2_MAGIC) {
+		parse_psf2(bytes)
+	} else if bytes.starts_with(&PSF1_MAGIC) {
+		parse_psf1(bytes)
+	} else {
+		bail!("Expecting PSF1 or PSF2 magic");
+	}
+}
+
+fn parse_psf1(bytes: &[u8]) -> Result<Font> {
+	let Some(&[mode, height]) = bytes.get(2..4) else {
+		bail!("PSF1 header truncated");
+	};
+	let height = height as usize;
+	if height == 0 {
+		bail!("PSF1 height 0 not supported");
+	}
+	let length: usize = if mode & PSF1_MODE_512 != 0 { 512 } else { 256 };
+	let Some(end) = length.checked_mul(height).and_then(|size| size.checked_add(4)) else {
+		bail!("PSF1 glyph bitmaps of {length} x {height} bytes too large");
+	};
+	let Some(bitmaps) = bytes.get(4..end) else {
+		bail!("PSF1 glyph bitmaps truncated");
+	};
+	let mut table = Vec::new();
+	if mode & PSF1_MODE_HAS_TABLE != 0 {
+		let mut entries = Vec::new();
+		let mut sequence = false;
+		for pair in bytes[end..].chunks_exact(2) {
+			let value = u16::from_le_bytes([pair[0], pair[1]]);
+			if value == PSF1_SEPARATOR {
+				table.push(std::mem::take(&mut entries));
+				sequence = false;
+			} else if value == PSF1_START_SEQUENCE {
+				sequence = true;
+			} else if !sequence {
+				entries.push(value as usize);
+			}
+		}
+	}
+	font(8, height, bitmaps, &table)
+}
+
+fn parse_psf2(bytes: &[u8]) -> Result<Font> {
+	if bytes.len() < PSF2_HEADER_SIZE {
+		bail!("PSF2 header truncated");
+	}
+	let word = |index: usize| {
+		let offset = index * 4;
+		u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+	};
+	let version = word(1);
+	if version != 0 {
+		bail!("PSF2 version {version} not supported");
+	}
+	let header_size = word(2) as usize;
+	let flags = word(3);
+	let length = word(4) as usize;
+	let char_size = word(5) as usize;
+	let height = word(6) as usize;
+	let width = word(7) as usize;
+	if width == 0 || width > usize::BITS as usize {
+		bail!("PSF2 width {width} not supported");
+	}
+	if height == 0 {
+		bail!("PSF2 height 0 not supported");
+	}
+	if Some(char_size) != width.div_ceil(8).checked_mul(height) {
+		bail!("PSF2 charsize {char_size} does not match {width}x{height}");
+	}
+	let Some(end) = length.checked_mul(char_size)
+		.and_then(|size| size.checked_add(header_size)) else {
+		bail!("PSF2 glyph bitmaps of {length} x {char_size} bytes too large");
+	};
+	let Some(bitmaps) = bytes.get(header_size..end) else {
+		bail!("PSF2 glyph bitmaps truncated");
+	};
+	let mut table = Vec::new();
+	if flags & PSF2_HAS_UNICODE_TABLE != 0 {
+		for entry in bytes[end..].split(|&byte| byte == PSF2_SEPARATOR) {
+			// code points before any sequences, sequences are not supported
+			let entry = entry.split(|&byte| byte == PSF2_START_SEQUENCE)
+				.next()
+				.unwrap_or(&[]);
+			let Ok(entry) = std::str::from_utf8(entry) else {
+				bail!("PSF2 unicode table is not UTF-8");
+			};
+			table.push(entry.chars().map(|c| c as usize).collect());
+		}
+		table.truncate(length);
+	}
+	font(width, height, bitmaps, &table)
+}
+
+/// Glyph `i` is mapped to the code points in `table[i]`, or to code point
+/// `i` without a table.
+fn font(width: usize, height: usize, bitmaps: &[u8], table: &[Vec<usize>]) -> Result<Font> {
+	let mut font = Font::new("", width, height, height)?;
+	let row_size = width.div_ceil(8);
+	let pad = (row_size * 8) - width;
+	for (index, bitmap) in bitmaps.chunks_exact(row_size * height).enumerate() {
+		let rows = bitmap.chunks_exact(row_size)
+			.map(|row| {
+				row.iter().fold(0usize, |value, &byte| (value << 8) | byte as usize) >> pad
+			})
+			.collect();
+		let glyph = Glyph {
+			name: format!("glyph{index}"),
+			width,
+			height,
+			rows,
+			advance: width as isize,
+		};
+		if table.is_empty() {
+			font.glyphs.insert(index, glyph);
+		} else if let Some(code_points) = table.get(index) {
+			for &code_point in code_points {
+				font.glyphs.insert(code_point, glyph.clone());
+			}
+		}
+	}
+	Ok(font)
+}
+
+/// Serialize as PSF1, only for 8 pixel wide fonts of at most 512 glyphs,
+/// the glyphs in key order. The unicode table maps each glyph to the code
+/// point `unicode` gives for its key, if any.
+pub fn write_psf1(font: &Font, unicode: impl Fn(usize) -> Option<usize>) -> Result<Vec<u8>> {
+	if font.width != 8 {
+		bail!("PSF1 fonts must be 8 pixels wide, found {}", font.width);
+	}
+	if font.height > 255 {
+		bail!("PSF1 fonts must be at most 255 pixels high, found {}", font.height);
+	}
+	let codes = codes(font);
+	let length = match codes.len() {
+		0..=256 => 256,
+		257..=512 => 512,
+		count => bail!("PSF1 fonts hold at most 512 glyphs, found {count}"),
+	};
+	let mut mode = PSF1_MODE_HAS_TABLE;
+	if length == 512 {
+		mode |= PSF1_MODE_512;
+	}
+	let mut psf = Vec::new();
+	psf.extend_from_slice(&PSF1_MAGIC);
+	psf.push(mode);
+	psf.push(font.height as u8);
+	bitmaps(font, &codes, length, &mut psf);
+	for index in 0..length {
+		if let Some(&code) = codes.get(index)
+			&& let Some(code_point) = unicode(code)
+			&& let Ok(code_point) = u16::try_from(code_point)
+		{
+			psf.extend_from_slice(&code_point.to_le_bytes());
+		}
+		psf.extend_from_slice(&PSF1_SEPARATOR.to_le_bytes());
+	}
+	Ok(psf)
+}
+
+/// Serialize as PSF2 with a unicode table, the glyphs in key order, each
+/// mapped to the code point `unicode` gives for its key, if any.
+pub fn write_psf2(font: &Font, unicode: impl Fn(usize) -> Option<usize>) -> Vec<u8> {
+	let codes = codes(font);
+	let length = codes.len();
+	let char_size = font.width.div_ceil(8) * font.height;
+	let mut psf = Vec::new();
+	psf.extend_from_slice(&PSF2_MAGIC);
+	for word in [
+		0,
+		PSF2_HEADER_SIZE,
+		PSF2_HAS_UNICODE_TABLE as usize,
+		length,
+		char_size,
+		font.height,
+		font.width,
+	] {
+		psf.extend_from_slice(&(word as u32).to_le_bytes());
+	}
+	bitmaps(font, &codes, length, &mut psf);
+	for code in codes {
+		if let Some(code_point) = unicode(code)
+			&& let Some(c) = char::from_u32(code_point as u32)
+		{
+			let mut utf8 = [0; 4];
+			psf.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+		}
+		psf.push(PSF2_SEPARATOR);
+	}
+	psf
+}
+
+fn codes(font: &Font) -> Vec<usize> {
+	let mut codes: Vec<usize> = font.glyphs.keys().copied().collect();
+	codes.sort();
+	codes
+}
+
+/// Glyph bitmaps of `codes` in order, padded with blank glyphs to `length`.
+fn bitmaps(font: &Font, codes: &[usize], length: usize, psf: &mut Vec<u8>) {
+	let row_size = font.width.div_ceil(8);
+	let pad = (row_size * 8) - font.width;
+	for index in 0..length {
+		let glyph = match codes.get(index) {
+			Some(code) => &font.glyphs[code],
+			None => &font.blank,
+		};
+		for row in &glyph.rows {
+			let row = row << pad;
+			for byte in (0..row_size).rev() {
+				psf.push((row >> (byte * 8)) as u8);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn psf1_round_trip() {
+		let mut font = Font::new("", 8, 2, 2).unwrap();
+		font.glyph_add(65, vec![0x81, 0x7E]);
+		let psf = write_psf1(&font, Some).unwrap();
+		let parsed = parse(&psf).unwrap();
+		assert_eq!((parsed.width, parsed.height), (8, 2));
+		assert_eq!(parsed.glyph(65).unwrap().rows, vec![0x81, 0x7E]);
+	}
+
+	#[test]
+	fn unicode_table() {
+		// ROM codes 0..3, only 1 and 2 showing a code point
+		let mut font = Font::new("", 8, 2, 2).unwrap();
+		for code in 0..4 {
+			font.glyph_add(code, vec![code, 0xFF]);
+		}
+		let unicode = |code| match code {
+			1 => Some(0x2500),
+			2 => Some(0x41),
+			_ => None,
+		};
+		let psf2 = write_psf2(&font, unicode);
+		// glyph positions are the ROM codes
+		let bitmaps = &psf2[PSF2_HEADER_SIZE..];
+		assert_eq!(&bitmaps[..8], &[0, 0xFF, 1, 0xFF, 2, 0xFF, 3, 0xFF]);
+		for psf in [psf2, write_psf1(&font, unicode).unwrap()] {
+			let parsed = parse(&psf).unwrap();
+			assert_eq!(parsed.glyph(0x2500).unwrap().rows, vec![1, 0xFF]);
+			assert_eq!(parsed.glyph(0x41).unwrap().rows, vec![2, 0xFF]);
+			assert_eq!(parsed.glyphs.len(), 2);
+		}
+	}
+
+	#[test]
+	fn zero_height() {
+		assert!(parse(&[0x36, 0x04, 0x00, 0x00]).is_err());
+		let mut psf2 = PSF2_MAGIC.to_vec();
+		// version, header size, flags, length, charsize, height, width
+		for word in [0, PSF2_HEADER_SIZE as u32, 0, 256, 0, 0, 8] {
+			psf2.extend_from_slice(&word.to_le_bytes());
+		}
+		assert!(parse(&psf2).is_err());
+	}
+
+	#[test]
+	fn psf2_oversized() {
+		let mut psf2 = PSF2_MAGIC.to_vec();
+		for word in [0, PSF2_HEADER_SIZE as u32, 0, u32::MAX, 8, 8, 8] {
+			psf2.extend_from_slice(&word.to_le_bytes());
+		}
+		assert!(parse(&psf2).is_err());
+	}
+}
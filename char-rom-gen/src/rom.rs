@@ -0,0 +1,679 @@
+//! The character ROM, a font of any cell size with synthesized control and
+//! hex glyphs.
+
+use anyhow::{
+    Context,
+    Result,
+    bail,
+};
+use gowin_bsram::Primitive;
+use gowin_bsram::decode;
+use gowin_bsram::memory::{
+    Layout,
+    Memory,
+    Read,
+    Style,
+};
+use std::collections::BTreeMap;
+
+use crate::bdf::{
+    self,
+    Font,
+    Glyph,
+};
+use crate::charset::Charset;
+use crate::double;
+use crate::image::Image;
+use crate::overrides::Override;
+use crate::preview;
+use crate::psf;
+use crate::source::{
+    Cache,
+    Source,
+};
+
+/// Where the fonts of the ROM come from.
+pub struct Fonts {
+    /// Primary font, its cell size sets the geometry of the ROM
+    pub font: Source,
+    /// Small capital letters of "^C" style control glyphs
+    pub control: Source,
+    /// Digits of the diagonal hex glyphs for 128..255
+    pub hex: Source,
+    /// Bold font of the same cell size, for an attribute-aware ROM
+    pub bold: Option<Source>,
+}
+
+impl Default for Fonts {
+    fn default() -> Self {
+        Fonts {
+            font: Source::Cache("Tamzen10x20r.bdf".into()),
+            control: Source::Cache("Tamzen7x14r.bdf".into()),
+            hex: Source::Cache("Tamzen7x13r.bdf".into()),
+            bold: None,
+        }
+    }
+}
+
+/// The "^" hat of the control glyphs.
+const HAT: [&str; 3] = [
+    "..#..",
+    ".#.#.",
+    "#...#",
+];
+
+/// How a glyph of the ROM is synthesized instead of taken from the font.
+enum Synthesized {
+    /// Caret notation, "^C" for ETX, with a small capital letter
+    Caret(char),
+    /// The code as two diagonal hex digits
+    Hex,
+}
+
+/// The synthesized glyphs: caret notation for the C0 controls and DEL, hex
+/// for the upper half.
+fn synthesized(index: usize) -> Option<Synthesized> {
+    match index {
+        0x01..=0x1F => Some(Synthesized::Caret(char::from(0x40 + index as u8))),
+        0x7F => Some(Synthesized::Caret('?')),
+        0x80..=0xFF => Some(Synthesized::Hex),
+        _ => None,
+    }
+}
+
+pub struct Rom {
+    /// The primary font including the synthesized glyphs
+    pub font: Font,
+    /// The primary font as loaded, for the glyphs of other charsets
+    pub unicode: Font,
+    /// The bold font as loaded, adding a bold bank to each charset and the
+    /// bold, underline and reverse attribute inputs
+    pub bold: Option<Font>,
+    /// Charsets of the ROM banks, the ASCII one by default
+    pub charsets: Vec<Charset>,
+    /// Mapping of the upper half shared by all banks, instead of the hex
+    /// glyphs
+    pub upper: Option<Charset>,
+    /// Add the double width and double height half glyphs of each bank
+    pub double: bool,
+    /// Smooth the diagonals of the double height glyphs
+    pub smooth: bool,
+    /// Gowin primitives, or a behavioural ROM loading [`Rom::memory`]
+    pub style: Style,
+    /// The hex digit font, also used for labels
+    pub hex: Font,
+    /// Provenance of the fonts, as Verilog comments
+    header: String,
+}
+
+impl Rom {
+    pub fn load(cache: &Cache, fonts: &Fonts) -> Result<Self> {
+        let bold = match &fonts.bold {
+            Some(source) => Some(load(cache, source)?),
+            None => None,
+        };
+        Self::from_fonts(fonts, load(cache, &fonts.font)?, &load(cache, &fonts.control)?, load(cache, &fonts.hex)?, bold)
+    }
+
+    /// The ROM of fonts already read from `fonts`, named in errors and the
+    /// header.
+    pub fn from_fonts(fonts: &Fonts, unicode: Font, control: &Font, hex: Font, bold: Option<Font>) -> Result<Self> {
+        let mut font = unicode.clone();
+
+        if font.width == 0 || font.width > 64 || font.height == 0 {
+            bail!("{} has unsupported cell size {}x{}", fonts.font, font.width, font.height);
+        }
+
+        // the synthesized "^C" style control glyphs and hex glyphs
+        let hat = Glyph::from_art("hat", &HAT)?;
+        let cell = Glyph::blank(font.width, font.height);
+        for index in 0..256 {
+            let glyph = match synthesized(index) {
+                None => continue,
+                // the "^" hat top left, the small capital letter right
+                // aligned, both centred vertically
+                Some(Synthesized::Caret(letter)) => {
+                    let letter = glyph(control, &fonts.control, letter)?;
+                    let top = (font.height as isize - letter.height as isize) / 2;
+                    cell.clone()
+                        .xor(&hat, 0, top)
+                        .xor(letter, font.width as isize - letter.width as isize, top)
+                }
+                // the high digit top left, one row up into the digit's blank
+                // ascent, the low digit bottom right
+                Some(Synthesized::Hex) => {
+                    let high = glyph(&hex, &fonts.hex, hex_digit(index >> 4))?;
+                    let low = glyph(&hex, &fonts.hex, hex_digit(index & 15))?;
+                    cell.clone()
+                        .xor(high, 0, -1)
+                        .xor(low, font.width as isize - low.width as isize, font.height as isize - low.height as isize)
+                }
+            };
+            font.glyph_add(index, glyph.rows);
+        }
+
+        let mut header = String::new();
+        header.push_str("// Generated by char-rom-gen, do not edit.\n");
+        provenance(&fonts.font, &font, &mut header);
+        provenance(&fonts.control, control, &mut header);
+        provenance(&fonts.hex, &hex, &mut header);
+
+        let bold = match (&fonts.bold, bold) {
+            (Some(source), Some(bold)) => {
+                if bold.width != font.width || bold.height != font.height {
+                    bail!("{source} must be {}x{} like {}, found {}x{}",
+                        font.width, font.height, fonts.font, bold.width, bold.height);
+                }
+                provenance(source, &bold, &mut header);
+                Some(bold)
+            }
+            _ => None,
+        };
+
+        Ok(Rom {
+            font,
+            unicode,
+            bold,
+            charsets: vec![Charset::ascii()],
+            upper: None,
+            double: false,
+            smooth: false,
+            style: Style::Gowin,
+            hex,
+            header,
+        })
+    }
+
+    /// Replace the glyphs of Unicode code points in all banks and charsets,
+    /// below 0x80 also the glyph the ASCII bank shows at that code, which
+    /// for the C0 controls and DEL is synthesized.
+    pub fn add_overrides(&mut self, source: &str, overrides: &[Override]) {
+        self.header.push_str("//\n");
+        self.header.push_str(&format!("// {source}\n"));
+        for Override { code_point, glyph } in overrides {
+            self.header.push_str(&format!("//   U+{code_point:04X} {}\n", glyph.name));
+            self.unicode.glyphs.insert(*code_point, glyph.clone());
+            if let Some(bold) = &mut self.bold {
+                bold.glyphs.insert(*code_point, glyph.clone());
+            }
+            // ROM codes 0x80 and up are not their code point, the hex
+            // glyphs or the --upper mapping of self.unicode
+            if *code_point < 0x80 {
+                self.font.glyphs.insert(*code_point, glyph.clone());
+            }
+        }
+    }
+
+    /// The 256 glyphs of the ASCII bank exactly as they appear in the ROM.
+    pub fn glyphs(&self) -> Font {
+        let mut rom = self.font.clone();
+        rom.glyphs.clear();
+        for index in 0..256 {
+            let mut glyph = self.font.glyph_or_default(index).clone();
+            if self.font.glyph(index).is_none() {
+                glyph.name = format!("U+{index:04X}");
+            }
+            rom.glyphs.insert(index, glyph);
+        }
+        match &self.upper {
+            Some(upper) => upper.bank(&rom, &self.unicode),
+            None => rom,
+        }
+    }
+
+    /// The 256 bold glyphs, those of the bold font for printable ASCII and
+    /// the mapped upper half, the plain ones otherwise.
+    pub fn bold_glyphs(&self) -> Option<Font> {
+        let bold = self.bold.as_ref()?;
+        let mut rom = self.glyphs();
+        for index in 0x21..0x7F {
+            if let Some(glyph) = bold.glyph(index) {
+                rom.glyphs.insert(index, glyph.clone());
+            }
+        }
+        Some(match &self.upper {
+            Some(upper) => upper.bank(&rom, bold),
+            None => rom,
+        })
+    }
+
+    /// The named 256 glyphs of each bank, the plain and the bold one of each
+    /// charset.
+    pub fn banks(&self) -> Vec<(String, Font)> {
+        let plain = self.glyphs();
+        let bold = self.bold_glyphs();
+        let mut banks = Vec::new();
+        for charset in &self.charsets {
+            banks.push((charset.name.clone(), charset.bank(&plain, &self.unicode)));
+            if let (Some(glyphs), Some(font)) = (&bold, &self.bold) {
+                // the mapped plain glyphs where the bold font lacks them
+                let bank = charset.bank(&charset.bank(glyphs, &self.unicode), font);
+                banks.push((format!("{} bold", charset.name), bank));
+            }
+        }
+        banks
+    }
+
+    /// Pixel row of the underline, `UNDERLINE_POSITION` pixels below the
+    /// baseline, by default half the descent.
+    pub fn underline_row(&self) -> usize {
+        let position = self.unicode.property_integer("UNDERLINE_POSITION")
+            .unwrap_or((self.unicode.descent as isize / 2).max(1));
+        (self.unicode.ascent as isize + position - 1).clamp(0, self.unicode.height as isize - 1) as usize
+    }
+
+    /// The named 256 glyph regions of the ROM, the shapes of each bank when
+    /// double size lines are enabled otherwise just the banks.
+    pub fn regions(&self) -> Vec<(String, Font)> {
+        let mut regions = Vec::new();
+        for (bank_name, bank) in self.banks() {
+            if !self.double {
+                regions.push((bank_name, bank));
+                continue;
+            }
+            for (name, shape) in double::SHAPES {
+                regions.push((format!("{bank_name} {name}"), double::shape(&bank, shape, self.smooth)));
+            }
+        }
+        regions
+    }
+
+    /// Unicode code point of each ROM code of region `index` that shows the
+    /// glyph of one: printable ASCII and the mapped codes of its charset and
+    /// the upper half. The synthesized control and hex glyphs, and all
+    /// glyphs of the double size halves, have none.
+    pub fn code_points(&self, index: usize) -> BTreeMap<usize, usize> {
+        let mut code_points = BTreeMap::new();
+        let shapes = if self.double { double::SHAPES.len() } else { 1 };
+        let weights = if self.bold.is_some() { 2 } else { 1 };
+        let Some(charset) = self.charsets.get(index / (shapes * weights)) else {
+            return code_points;
+        };
+        if !index.is_multiple_of(shapes) {
+            return code_points;
+        }
+        for code in 0x20..0x7F {
+            if self.unicode.glyph(code).is_some() {
+                code_points.insert(code, code);
+            }
+        }
+        // codes the font has no glyph for keep the one below
+        for charset in self.upper.iter().chain([charset]) {
+            let missing = charset.missing(&self.unicode);
+            for (&code, &code_point) in &charset.map {
+                if !missing.contains(&(code, code_point)) {
+                    code_points.insert(code, code_point);
+                }
+            }
+        }
+        code_points
+    }
+
+    /// Glyph sheet of the 256 glyphs of a region, see [`preview::render`].
+    pub fn preview(&self, region: &Font) -> Image {
+        preview::render(region, &self.hex)
+    }
+
+    /// BSRAM layout and utilization of the ROM.
+    pub fn layout(&self) -> Result<Layout> {
+        layout(&self.font, self.regions().len())
+    }
+
+    /// Content of the ROM, one pixel row per entry at address
+    /// `{glyph_row, char}`.
+    pub fn memory(&self) -> Result<Memory> {
+        memory(&self.regions())
+    }
+
+    /// Decode the blocks of a generated `char_rom` through their `CE` and
+    /// `AD` address mapping and compare every pixel row with the glyphs,
+    /// failing with the first mismatches.
+    pub fn verify(&self, verilog: &str) -> Result<()> {
+        let regions = self.regions();
+        let height = self.font.height;
+        let q = glyph_output(self.bold.is_some(), gated(regions.len(), height));
+        let content = decode::decode(verilog, "block", "addr", q, address_width(regions.len() * height) + 8)?;
+        let mut mismatches = Vec::new();
+        for (addr, &found) in content.iter().enumerate() {
+            // addr = {glyph_row, char}
+            let (glyph_row, code) = (addr >> 8, addr & 0xFF);
+            let (region, row) = (glyph_row / height, glyph_row % height);
+            let (name, expected) = match regions.get(region) {
+                Some((name, font)) => (name.as_str(), font.glyph_or_default(code).rows[row] as u64),
+                None => ("unused", 0),
+            };
+            if found != expected {
+                mismatches.push(format!("{name} {code:#04X} row {row}: expected {expected:0w$b}, found {found:0w$b}",
+                    w = self.font.width));
+            }
+        }
+        if !mismatches.is_empty() {
+            let count = mismatches.len();
+            mismatches.truncate(8);
+            bail!("{count} pixel row(s) differ from the fonts\n{}", mismatches.join("\n"));
+        }
+        Ok(())
+    }
+
+    pub fn verilog(&self) -> Result<String> {
+        let mut verilog = String::with_capacity(30000);
+        verilog.push_str(&self.header);
+        verilog.push_str("\n");
+        char_rom(self, &self.regions(), &mut verilog)?;
+        Ok(verilog)
+    }
+}
+
+/// The glyph of `font` for `character`.
+fn glyph<'a>(font: &'a Font, source: &Source, character: char) -> Result<&'a Glyph> {
+    match font.glyph(character as usize) {
+        Some(glyph) => Ok(glyph),
+        None => bail!("{source} has no glyph for '{character}'"),
+    }
+}
+
+fn hex_digit(digit: usize) -> char {
+    char::from_digit(digit as u32, 16).expect("hex")
+        .to_ascii_uppercase()
+}
+
+/// Read a BDF or PSF font.
+pub fn load(cache: &Cache, source: &Source) -> Result<Font> {
+    let bytes = source.read(cache)?;
+    if psf::is_psf(&bytes) {
+        return psf::parse(&bytes).with_context(|| format!("{source}"));
+    }
+    let bdf = std::str::from_utf8(&bytes)
+        .with_context(|| format!("{source} is not UTF-8"))?;
+    bdf::parse(bdf).with_context(|| format!("{source}"))
+}
+
+fn provenance(source: &Source, font: &Font, verilog: &mut String) {
+    verilog.push_str("//\n");
+    verilog.push_str(&format!("// {source}\n"));
+    if !font.name.is_empty() {
+        verilog.push_str(&format!("//   FONT {}\n", font.name));
+    }
+    for name in ["FAMILY_NAME", "COPYRIGHT", "NOTICE"] {
+        if let Some(value) = font.property(name) {
+            verilog.push_str(&format!("//   {name} {value}\n"));
+        }
+    }
+}
+
+fn char_rom(rom: &Rom, regions: &[(String, Font)], verilog: &mut String) -> Result<()> {
+    // address {glyph_row, char}, glyph_row counting the rows of all regions
+    let font = &regions[0].1;
+    let row_width = address_width(font.height);
+    let glyph_row_width = address_width(regions.len() * font.height);
+    let memory = memory(regions)?;
+    let banks = rom.charsets.len();
+    let attributes = rom.bold.is_some();
+
+    verilog.push_str("`default_nettype none\n");
+    verilog.push_str("`timescale 1ns / 1ps\n");
+    if font.height < 1 << row_width {
+        verilog.push_str(&format!("// {}x{} cells, rows {}..{} read as zero\n",
+            font.width, font.height, font.height, (1 << row_width) - 1));
+    }
+    if attributes {
+        verilog.push_str(&format!("// underline row {}\n", rom.underline_row()));
+    }
+    if regions.len() > 1 {
+        for (index, (name, _)) in regions.iter().enumerate() {
+            verilog.push_str(&format!("// region {index}: {name}\n"));
+        }
+    }
+    verilog.push_str("module char_rom\n");
+    verilog.push_str("(\n");
+    verilog.push_str("    input wire clk,\n");
+    verilog.push_str("    input wire ce,\n");
+    if banks > 2 {
+        verilog.push_str(&format!("    input wire [{}:0] bank,\n", address_width(banks) - 1));
+    } else if banks > 1 {
+        verilog.push_str("    input wire bank,\n");
+    }
+    if attributes {
+        verilog.push_str("    input wire bold,\n");
+        verilog.push_str("    input wire underline,\n");
+        verilog.push_str("    input wire reverse,\n");
+    }
+    if rom.double {
+        verilog.push_str("    // 0 single, 1 double width, 2 double height top, 3 bottom\n");
+        verilog.push_str("    input wire [1:0] line,\n");
+        verilog.push_str("    // right half of a double width or height glyph\n");
+        verilog.push_str("    input wire half,\n");
+    }
+    verilog.push_str("    input wire [7:0] char,\n");
+    verilog.push_str(&format!("    input wire [{}:0] row,\n", row_width - 1));
+    verilog.push_str(&format!("    output wire [{}:0] q\n", font.width - 1));
+    verilog.push_str(");\n");
+    verilog.push_str("\n");
+    if regions.len() > 1 {
+        // region = ((bank * weights) + bold) * shapes + shape
+        let mut region: Option<String> = None;
+        let mut select = |name: &str, count: usize| {
+            region = Some(match region.take() {
+                Some(outer) => format!("(({outer}) * {glyph_row_width}'d{count}) + {name}"),
+                None => name.to_owned(),
+            });
+        };
+        if banks > 1 {
+            select("bank", banks);
+        }
+        if attributes {
+            select("bold", 2);
+        }
+        if rom.double {
+            verilog.push_str("    wire [2:0] shape = (line == 2'd0) ? 3'd0 : {line, half} - 3'd1;\n");
+            select("shape", double::SHAPES.len());
+        }
+        let region = region.expect("region select");
+        verilog.push_str(&format!("    wire [{}:0] glyph_row = (({region}) * {glyph_row_width}'d{}) + row;\n",
+            glyph_row_width - 1, font.height));
+        verilog.push_str(&format!("    wire [{}:0] addr = {{glyph_row, char}};\n", glyph_row_width + 7));
+    } else {
+        verilog.push_str(&format!("    wire [{}:0] addr = {{row, char}};\n", row_width + 7));
+    }
+    verilog.push_str("\n");
+    let gate = gated(regions.len(), font.height);
+    let q = glyph_output(attributes, gate);
+    if q != "q" {
+        verilog.push_str(&format!("    wire [{}:0] glyph;\n", font.width - 1));
+    }
+    if gate {
+        // glyph_row of the rows past the height would be the first rows of
+        // the next region
+        verilog.push_str("    reg in_glyph;\n");
+    }
+    if attributes {
+        verilog.push_str("    reg underline_row;\n");
+        verilog.push_str("    reg reverse_row;\n");
+    }
+    if gate || attributes {
+        verilog.push_str("\n");
+        verilog.push_str("    always @(posedge clk) begin\n");
+        verilog.push_str("        if (ce) begin\n");
+        if gate {
+            verilog.push_str(&format!("            in_glyph <= (row < {row_width}'d{});\n", font.height));
+        }
+        if attributes {
+            verilog.push_str(&format!("            underline_row <= underline & (row == {row_width}'d{});\n", rom.underline_row()));
+            verilog.push_str("            reverse_row <= reverse;\n");
+        }
+        verilog.push_str("        end\n");
+        verilog.push_str("    end\n");
+        verilog.push_str("\n");
+    }
+    let read = Read {
+        clk: "clk",
+        ce: "ce",
+        address: "addr",
+        q,
+    };
+    memory.emit("block", &rom.style, &read, None, verilog)?;
+    let glyph = if gate { format!("(in_glyph ? glyph : {}'b0)", font.width) } else { String::from("glyph") };
+    if attributes {
+        verilog.push_str(&format!("    assign q = (underline_row ? {{{0}{{1'b1}}}} : {glyph}) ^ {{{0}{{reverse_row}}}};\n", font.width));
+        verilog.push_str("\n");
+    } else if gate {
+        verilog.push_str(&format!("    assign q = {glyph};\n"));
+        verilog.push_str("\n");
+    }
+    verilog.push_str("endmodule\n");
+    Ok(())
+}
+
+/// Whether rows past the glyph `height` are gated to zero, needed with
+/// several regions as `glyph_row = region * height + row` packs them
+/// without gaps.
+pub(crate) fn gated(regions: usize, height: usize) -> bool {
+    regions > 1 && height < 1 << address_width(height)
+}
+
+/// Output of the blocks, `q` unless attributes or the gate come after them.
+pub(crate) fn glyph_output(attributes: bool, gated: bool) -> &'static str {
+    if attributes || gated { "glyph" } else { "q" }
+}
+
+/// The pixel rows of all glyphs of `regions`, glyph row major.
+fn memory(regions: &[(String, Font)]) -> Result<Memory> {
+    let font = &regions[0].1;
+    let glyph_row_width = address_width(regions.len() * font.height);
+    let mut memory = Memory::new(layout(font, regions.len())?, glyph_row_width + 8)?;
+    for (index, (_, region)) in regions.iter().enumerate() {
+        for row in 0..font.height {
+            for glyph in 0..256 {
+                let bits = region.glyph_or_default(glyph).rows[row];
+                memory.set((((index * font.height) + row) * 256) + glyph, bits as u64)?;
+            }
+        }
+    }
+    Ok(memory)
+}
+
+/// BSRAM layout of `regions` of 256 glyphs, one entry per pixel row.
+fn layout(font: &Font, regions: usize) -> Result<Layout> {
+    Layout::plan(Primitive::Prom, regions * font.height * 256, font.width)
+}
+
+/// Number of address bits for `count` entries, at least one.
+pub(crate) fn address_width(count: usize) -> usize {
+    (usize::BITS - (count.max(2) - 1).leading_zeros()) as usize
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A `width` x `height` font with a pseudo random glyph for each of
+    /// `codes`, parsed from BDF built in memory.
+    pub(crate) fn font(width: usize, height: usize, codes: impl Iterator<Item = usize>, seed: u64) -> Font {
+        let mut text = format!("STARTFONT 2.1\nFONT test\nFONTBOUNDINGBOX {width} {height} 0 0\nCHARS 0\n");
+        let digits = width.div_ceil(8) * 2;
+        let mut state = seed;
+        for code in codes {
+            text.push_str(&format!("STARTCHAR U+{code:04X}\nENCODING {code}\nBBX {width} {height} 0 0\nBITMAP\n"));
+            for _ in 0..height {
+                state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+                let row = (state >> 33) as usize & ((1 << width) - 1);
+                text.push_str(&format!("{:0digits$X}\n", row << ((digits * 4) - width)));
+            }
+            text.push_str("ENDCHAR\n");
+        }
+        text.push_str("ENDFONT\n");
+        bdf::parse(&text).unwrap()
+    }
+
+    /// A ROM of a 10x20 primary font with printable ASCII and the box
+    /// drawing glyphs of the charsets.
+    pub(crate) fn rom() -> (Font, Rom) {
+        let unicode = font(10, 20, (0x20..0x7F).chain(0x2500..0x2580).chain([0xA3, 0xB0]), 1);
+        let control = font(7, 14, 0x20..0x7F, 2);
+        let hex = font(7, 13, 0x20..0x7F, 3);
+        let rom = Rom::from_fonts(&Fonts::default(), unicode.clone(), &control, hex, None).unwrap();
+        (unicode, rom)
+    }
+
+    #[test]
+    fn decode_round_trip() {
+        let (unicode, rom) = rom();
+        // the hand written layout this replaced: bits 7:0 over three
+        // blocks selected by the upper address bits, bits 9:8 in one 2 bit
+        // wide block
+        let layout = rom.layout().unwrap();
+        assert!(layout.slices.iter().any(|slice| slice.blocks(layout.depth) > 1));
+        assert!(layout.slices.iter().any(|slice| slice.bit_width == 2));
+
+        let verilog = rom.verilog().unwrap();
+        let content = decode::decode(&verilog, "block", "addr", "q", 5 + 8).unwrap();
+        for code in 0..256 {
+            // printable ASCII straight from the source font, the rest as
+            // synthesized
+            let glyph = match unicode.glyph(code) {
+                Some(glyph) if code < 0x80 => glyph,
+                _ => rom.font.glyph_or_default(code),
+            };
+            for row in 0..32 {
+                let expected = glyph.rows.get(row).map_or(0, |&bits| bits as u64);
+                assert_eq!(content[(row << 8) | code], expected, "{code:#04X} row {row}");
+            }
+        }
+        rom.verify(&verilog).unwrap();
+    }
+
+    #[test]
+    fn decode_banks() {
+        let (_, mut rom) = rom();
+        rom.charsets = ["ascii", "uk", "dec-special-graphics"].iter()
+            .map(|name| Charset::load(name))
+            .collect::<Result<_>>()
+            .unwrap();
+        let verilog = rom.verilog().unwrap();
+        rom.verify(&verilog).unwrap();
+        // rows 20..31 gated rather than reading the next bank
+        assert!(verilog.contains("in_glyph <= (row < 5'd20);"), "{verilog}");
+        assert!(verilog.contains("assign q = (in_glyph ? glyph : 10'b0);"), "{verilog}");
+    }
+
+    #[test]
+    fn bdf_encoding() {
+        let (_, mut rom) = rom();
+        rom.charsets = ["ascii", "dec-special-graphics"].iter()
+            .map(|name| Charset::load(name))
+            .collect::<Result<_>>()
+            .unwrap();
+        rom.upper = Some(Charset::load("cp437").unwrap());
+        let regions = rom.regions();
+        for (index, (name, region)) in regions.iter().enumerate() {
+            let code_points = rom.code_points(index);
+            let bdf = region.write(|code| code_points.get(&code).copied());
+            assert!(bdf.contains("ENCODING -1 1\n"), "{name}");
+            let parsed = bdf::parse(&bdf).unwrap();
+            let rows = |font: &Font, code| font.glyph(code).map(|glyph| glyph.rows.clone());
+            assert_eq!(rows(&parsed, 0x41), rows(region, 0x41), "{name}");
+            // cp437 0xC4, the font has U+2500
+            assert_eq!(rows(&parsed, 0x2500), rows(region, 0xC4), "{name}");
+            // cp437 0x80 is U+00C7, which the font lacks, so the hex glyph
+            assert!(parsed.glyph(0xC7).is_none(), "{name}");
+            // control and hex glyphs are not their code point
+            assert!(parsed.glyph(0x01).is_none() && parsed.glyph(0x80).is_none(), "{name}");
+        }
+        // DEC Special Graphics 0x71 is U+2500 too
+        assert_eq!(rom.code_points(0).get(&0x71), Some(&0x71));
+        assert_eq!(rom.code_points(1).get(&0x71), Some(&0x2500));
+    }
+
+    #[test]
+    fn decode_corrupt() {
+        let (_, rom) = rom();
+        let verilog = rom.verilog().unwrap();
+        // flip the low bits of the last hex digit of the first INIT_RAM
+        let start = verilog.find(".INIT_RAM_00(").unwrap();
+        let end = start + verilog[start..].find(')').unwrap();
+        let digit = u32::from_str_radix(&verilog[end - 1..end], 16).unwrap();
+        let flipped = char::from_digit(digit ^ 1, 16).unwrap();
+        let corrupt = format!("{}{flipped}{}", &verilog[..end - 1], &verilog[end..]);
+        assert!(rom.verify(&corrupt).is_err());
+    }
+}
@@ -0,0 +1,156 @@
+use anyhow::{
+    Context,
+    Result,
+    bail,
+};
+use sha2::{
+    Digest,
+    Sha256,
+};
+use std::collections::BTreeMap;
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+/// The vendored cache directory.
+pub const FONTS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fonts");
+
+/// Checksum file inside the cache directory, in `sha256sum` format so that
+/// `sha256sum -c SHA256SUMS` can verify the vendored fonts by hand.
+const SUMS: &str = "SHA256SUMS";
+
+/// Upstream location of fonts that may be fetched into the cache.
+#[cfg(feature = "fetch")]
+const UPSTREAM: &str = "https://github.com/sunaku/tamzen-font/raw/refs/heads/master/bdf/";
+
+/// Where a BDF (or PSF) font comes from.
+#[derive(Clone)]
+pub enum Source {
+    /// A BDF file on the local file system, used as is.
+    Path(PathBuf),
+    /// A font by name in the vendored cache directory, verified against
+    /// the recorded checksum.
+    Cache(String),
+}
+
+impl std::str::FromStr for Source {
+    type Err = std::convert::Infallible;
+
+    /// Anything that looks like a path is read directly, a bare file name
+    /// is looked up in the cache.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        if name.contains(std::path::MAIN_SEPARATOR) || name.contains('/') {
+            Ok(Source::Path(PathBuf::from(name)))
+        } else {
+            Ok(Source::Cache(name.to_owned()))
+        }
+    }
+}
+
+impl Source {
+    pub fn read(&self, cache: &Cache) -> Result<Vec<u8>> {
+        match self {
+            Source::Path(path) =>
+                std::fs::read(path)
+                    .with_context(|| format!("Could not read {}", path.display())),
+            Source::Cache(name) =>
+                cache.read(name),
+        }
+    }
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Path(path) =>
+                write!(f, "{}", path.display()),
+            Source::Cache(name) =>
+                write!(f, "{name}"),
+        }
+    }
+}
+
+/// Directory of vendored BDF files with their SHA-256 checksums.
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Cache {
+            dir: dir.into(),
+        }
+    }
+
+    /// The font `name`, fetched when missing with the `fetch` feature.
+    pub fn read(&self, name: &str) -> Result<Vec<u8>> {
+        let path = self.dir.join(name);
+        let sums = self.sums()?;
+        if !path.exists() {
+            return self.fetch(name, &path, sums);
+        }
+        let bytes = std::fs::read(&path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        let Some(expect) = sums.get(name) else {
+            bail!("No checksum for {name} in {}", self.dir.join(SUMS).display());
+        };
+        let actual = sha256(&bytes);
+        if &actual != expect {
+            bail!("Checksum mismatch for {}, expecting {expect} found {actual}", path.display());
+        }
+        Ok(bytes)
+    }
+
+    fn sums(&self) -> Result<BTreeMap<String, String>> {
+        let path = self.dir.join(SUMS);
+        let mut sums = BTreeMap::new();
+        if !path.exists() {
+            return Ok(sums);
+        }
+        let lines = std::fs::read_to_string(&path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        for line in lines.lines() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((sum, name)) = line.split_once("  ") else {
+                bail!("Expecting '<sha256>  <name>' in {}, found '{line}'", path.display());
+            };
+            sums.insert(name.to_owned(), sum.to_owned());
+        }
+        Ok(sums)
+    }
+
+    #[cfg(not(feature = "fetch"))]
+    fn fetch(&self, name: &str, path: &Path, _sums: BTreeMap<String, String>) -> Result<Vec<u8>> {
+        bail!("Font {name} not found at {}, copy it there or fetch the fonts with 'make fonts' in fpga", path.display());
+    }
+
+    #[cfg(feature = "fetch")]
+    fn fetch(&self, name: &str, path: &Path, sums: BTreeMap<String, String>) -> Result<Vec<u8>> {
+        // only fonts pinned in the committed checksum file are fetched, the
+        // upstream branch moves
+        let Some(expect) = sums.get(name) else {
+            bail!("No checksum for {name} in {}, not fetching it", self.dir.join(SUMS).display());
+        };
+        let url = format!("{UPSTREAM}{name}");
+        let bytes = reqwest::blocking::get(&url)
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.bytes())
+            .with_context(|| format!("Could not fetch {url}"))?;
+        let actual = sha256(&bytes);
+        if &actual != expect {
+            bail!("Checksum mismatch for {url}, expecting {expect} found {actual}");
+        }
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Could not create {}", self.dir.display()))?;
+        std::fs::write(path, &bytes)
+            .with_context(|| format!("Could not write {}", path.display()))?;
+        Ok(bytes.to_vec())
+    }
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
@@ -136,7 +136,8 @@ module top
             vt
             #(
                 .CLK(51_800_000),
-                .BAUD(115200)
+                .BAUD(115200),
+                .PORT(i)
             )
             vt
             (
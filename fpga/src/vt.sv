@@ -3,7 +3,8 @@
 module vt
 #(
     parameter CLK = 0, // MHz
-    parameter BAUD = 0 // Baud rate
+    parameter BAUD = 0, // Baud rate
+    parameter PORT = 0 // UART port, selecting the power-on screen
 )
 (
     input   wire        clk,
@@ -67,7 +68,11 @@ module vt
     // VRAM
     //==========================================
 
-    vram vram
+    vram
+    #(
+        .PORT(PORT)
+    )
+    vram
     (
         .clk(clk),
 
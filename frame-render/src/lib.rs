@@ -0,0 +1,194 @@
+//! The frame the `hdmi` module outputs, rendered on the host.
+//!
+//! This follows `hdmi_timings.sv`, `hdmi_text_mode.sv` and `hdmi.sv` pixel
+//! for pixel: 1024x600 active pixels of 10x20 cells, the screen starting at
+//! VRAM row `top_row` and wrapping around the 32 rows, the cursor cell
+//! inverted, and text column 100 showing the border in the colour of the
+//! active port. Columns 101 and 102 fill the last 14 pixels of each line
+//! with whatever VRAM holds there.
+
+use anyhow::{
+    Result,
+    bail,
+};
+use char_rom_gen::image::Image;
+use char_rom_gen::model::CharRom;
+
+/// Active pixels, `H_ACTIVE` and `V_ACTIVE` of `hdmi_timings.sv`.
+pub const WIDTH: usize = 1024;
+pub const HEIGHT: usize = 600;
+
+/// Cell size of `hdmi_text_mode.sv`.
+pub const CELL_WIDTH: usize = 10;
+pub const CELL_HEIGHT: usize = 20;
+
+/// Text column showing the border.
+pub const BORDER_COLUMN: usize = 100;
+
+/// VRAM geometry, `{row, col}` addressed.
+pub const VRAM_ROWS: usize = 32;
+pub const VRAM_COLUMNS: usize = 128;
+
+/// Palette of the rendered frame, indexed by the pixels of the image.
+const BLACK: u8 = 0;
+const GRAY: u8 = 1;
+const BORDER: u8 = 2;
+
+/// The inputs of the `hdmi` module for one frame.
+pub struct Screen {
+    /// VRAM content, `VRAM_ROWS` rows of `VRAM_COLUMNS` bytes
+    pub vram: Vec<u8>,
+    pub top_row: usize,
+    pub cursor_row: usize,
+    pub cursor_col: usize,
+    pub active_port: usize,
+}
+
+/// The frame of `screen`, its pixels indexing [`palette`].
+pub fn render(rom: &CharRom, screen: &Screen) -> Result<Image> {
+    if rom.width() != CELL_WIDTH || rom.height() != CELL_HEIGHT {
+        bail!("The hdmi pipeline shows {CELL_WIDTH}x{CELL_HEIGHT} cells, the ROM has {}x{}",
+            rom.width(), rom.height());
+    }
+    if screen.vram.len() != VRAM_ROWS * VRAM_COLUMNS {
+        bail!("VRAM of {} bytes, expecting {}", screen.vram.len(), VRAM_ROWS * VRAM_COLUMNS);
+    }
+    if screen.top_row >= VRAM_ROWS || screen.cursor_row >= VRAM_ROWS {
+        bail!("Rows run 0..{}", VRAM_ROWS - 1);
+    }
+    if screen.cursor_col >= VRAM_COLUMNS {
+        bail!("Columns run 0..{}", VRAM_COLUMNS - 1);
+    }
+    if screen.active_port >= 4 {
+        bail!("Ports run 0..3");
+    }
+
+    let mut image = Image::new(WIDTH, HEIGHT, BLACK);
+    for y in 0..HEIGHT {
+        // out_row counts on from top_row, wrapping in its 5 bits
+        let row = (screen.top_row + (y / CELL_HEIGHT)) % VRAM_ROWS;
+        let row_pixel = y % CELL_HEIGHT;
+        for col in 0..WIDTH.div_ceil(CELL_WIDTH) {
+            // out_border is only set on leaving column 99
+            if col == BORDER_COLUMN {
+                image.fill(col * CELL_WIDTH, y, CELL_WIDTH, 1, BORDER);
+                continue;
+            }
+            let byte = screen.vram[(row * VRAM_COLUMNS) + col];
+            let mut pixels = rom.q(byte, row_pixel) as usize;
+            if row == screen.cursor_row && col == screen.cursor_col {
+                pixels ^= (1 << CELL_WIDTH) - 1;
+            }
+            // stage4_pixels shift out from bit 9
+            image.draw(col * CELL_WIDTH, y, CELL_WIDTH, &[pixels], GRAY);
+        }
+    }
+    Ok(image)
+}
+
+/// Colours of the pixel values of [`render`] for `active_port`, the
+/// `{3{pixel, 7'b0}}` gray and the `border_rgb` of `hdmi.sv`.
+pub fn palette(active_port: usize) -> [[u8; 3]; 3] {
+    let border = match active_port {
+        0 => [0xC0, 0x00, 0x00],
+        1 => [0x00, 0xC0, 0x00],
+        2 => [0x00, 0x00, 0xC0],
+        _ => [0xC0, 0xC0, 0xC0],
+    };
+    [[0x00, 0x00, 0x00], [0x80, 0x80, 0x80], border]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use char_rom_gen::bdf::{
+        self,
+        Font,
+    };
+    use char_rom_gen::rom::{
+        Fonts,
+        Rom,
+    };
+
+    /// A `width` x `height` font of printable ASCII, every row of a glyph
+    /// the bits of its code, blank for the space.
+    fn font(width: usize, height: usize) -> Font {
+        let mut text = format!("STARTFONT 2.1\nFONT test\nFONTBOUNDINGBOX {width} {height} 0 0\nCHARS 0\n");
+        let digits = width.div_ceil(8) * 2;
+        for code in 0x20..0x7F {
+            let row = if code == 0x20 { 0 } else { code };
+            text.push_str(&format!("STARTCHAR U+{code:04X}\nENCODING {code}\nBBX {width} {height} 0 0\nBITMAP\n"));
+            for _ in 0..height {
+                text.push_str(&format!("{:0digits$X}\n", row << ((digits * 4) - width)));
+            }
+            text.push_str("ENDCHAR\n");
+        }
+        text.push_str("ENDFONT\n");
+        bdf::parse(&text).unwrap()
+    }
+
+    /// The pixels of `width` columns from `x` in line `y`, leftmost in the
+    /// most significant bit, failing on anything but gray and black.
+    fn bits(image: &Image, x: usize, y: usize, width: usize) -> usize {
+        (x..x + width).fold(0, |bits, x| {
+            let pixel = image.pixels[(y * WIDTH) + x];
+            assert!(pixel == GRAY || pixel == BLACK, "{pixel} at {x}, {y}");
+            (bits << 1) | usize::from(pixel == GRAY)
+        })
+    }
+
+    #[test]
+    fn frame() {
+        let rom = Rom::from_fonts(&Fonts::default(), font(10, 20), &font(7, 14), font(7, 13), None).unwrap();
+        let rom = CharRom::new(&rom).unwrap();
+        let mut vram = vec![b' '; VRAM_ROWS * VRAM_COLUMNS];
+        vram[0] = b'A';
+        // under the cursor
+        vram[VRAM_COLUMNS + 2] = b'A';
+        // under the border, then in the 14 pixels past it
+        vram[BORDER_COLUMN] = b'A';
+        vram[BORDER_COLUMN + 1] = b'A';
+        vram[BORDER_COLUMN + 2] = b'A';
+        let screen = Screen {
+            vram,
+            top_row: 0,
+            cursor_row: 1,
+            cursor_col: 2,
+            active_port: 1,
+        };
+        let image = render(&rom, &screen).unwrap();
+        let all = (1 << CELL_WIDTH) - 1;
+        for y in 0..CELL_HEIGHT {
+            assert_eq!(bits(&image, 0, y, CELL_WIDTH), 0x41, "A row {y}");
+            assert_eq!(bits(&image, CELL_WIDTH, y, CELL_WIDTH), 0, "space row {y}");
+            assert_eq!(bits(&image, 2 * CELL_WIDTH, CELL_HEIGHT + y, CELL_WIDTH), 0x41 ^ all, "cursor row {y}");
+            assert_eq!(bits(&image, 3 * CELL_WIDTH, CELL_HEIGHT + y, CELL_WIDTH), 0, "space row {y}");
+            assert_eq!(bits(&image, (BORDER_COLUMN + 1) * CELL_WIDTH, y, CELL_WIDTH), 0x41, "column 101 row {y}");
+            // the first 4 pixels of column 102
+            assert_eq!(bits(&image, (BORDER_COLUMN + 2) * CELL_WIDTH, y, 4), 0x41 >> 6, "column 102 row {y}");
+        }
+        for y in 0..HEIGHT {
+            for x in (BORDER_COLUMN * CELL_WIDTH)..((BORDER_COLUMN + 1) * CELL_WIDTH) {
+                assert_eq!(image.pixels[(y * WIDTH) + x], BORDER, "border at {x}, {y}");
+            }
+        }
+
+        // VRAM row 0 on the second line of cells, wrapping around
+        let image = render(&rom, &Screen { top_row: VRAM_ROWS - 1, ..screen }).unwrap();
+        assert_eq!(bits(&image, 0, 0, CELL_WIDTH), 0);
+        assert_eq!(bits(&image, 0, CELL_HEIGHT, CELL_WIDTH), 0x41);
+    }
+
+    #[test]
+    fn colours() {
+        for port in 0..4 {
+            let palette = palette(port);
+            assert_eq!(palette[usize::from(BLACK)], [0x00, 0x00, 0x00]);
+            assert_eq!(palette[usize::from(GRAY)], [0x80, 0x80, 0x80]);
+        }
+        assert_eq!(palette(0)[usize::from(BORDER)], [0xC0, 0x00, 0x00]);
+        assert_eq!(palette(1)[usize::from(BORDER)], [0x00, 0xC0, 0x00]);
+        assert_eq!(palette(2)[usize::from(BORDER)], [0x00, 0x00, 0xC0]);
+        assert_eq!(palette(3)[usize::from(BORDER)], [0xC0, 0xC0, 0xC0]);
+    }
+}
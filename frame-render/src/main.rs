@@ -0,0 +1,125 @@
+use anyhow::{
+    Context,
+    Result,
+    bail,
+};
+use clap::Parser;
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use char_rom_gen::model::CharRom;
+use char_rom_gen::rom::{
+    Fonts,
+    Rom,
+};
+use char_rom_gen::source::{
+    self,
+    Cache,
+};
+use frame_render::{
+    Screen,
+    VRAM_COLUMNS,
+    VRAM_ROWS,
+};
+use vram_gen::screen::Placeholders;
+
+/// Root of the repository, for the placeholders of the VRAM image.
+const ROOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/..");
+
+/// Render the frame the FPGA outputs over HDMI for a VRAM image.
+#[derive(Parser)]
+struct Args {
+    /// VRAM image, raw 4096 bytes for a `.bin` extension otherwise text in
+    /// the `vram-gen` `init.txt` format
+    #[arg(long, default_value = concat!(env!("CARGO_MANIFEST_DIR"), "/../vram-gen/init.txt"))]
+    vram: PathBuf,
+
+    /// VRAM row shown at the top of the screen
+    #[arg(long, default_value_t = 0)]
+    top_row: usize,
+
+    /// VRAM row of the cursor
+    #[arg(long, default_value_t = 0)]
+    cursor_row: usize,
+
+    /// Column of the cursor
+    #[arg(long, default_value_t = 0)]
+    cursor_col: usize,
+
+    /// Active UART port 0..3, setting the border colour and the `{PORT}`
+    /// and `{HOST}` of a text VRAM image
+    #[arg(long, default_value_t = 0)]
+    port: usize,
+
+    /// Read the glyphs from a generated `char_rom.sv` instead of generating
+    /// the default ROM from the fonts
+    #[arg(long)]
+    char_rom: Option<PathBuf>,
+
+    /// Directory of vendored fonts and their SHA256SUMS
+    #[arg(long, default_value = source::FONTS)]
+    fonts: PathBuf,
+
+    /// Scale factor of the image
+    #[arg(long, default_value_t = 1)]
+    scale: usize,
+
+    /// PNG file to write
+    #[arg(long, short, default_value = "frame.png")]
+    output: PathBuf,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let char_rom = match &args.char_rom {
+        // the geometry from the file, needing no fonts
+        Some(path) => CharRom::decode(&read(path)?)
+            .with_context(|| format!("{}", path.display()))?,
+        None => CharRom::new(&Rom::load(&Cache::new(&args.fonts), &Fonts::default())?)?,
+    };
+
+    let screen = Screen {
+        vram: vram(&args.vram, args.port)?,
+        top_row: args.top_row,
+        cursor_row: args.cursor_row,
+        cursor_col: args.cursor_col,
+        active_port: args.port,
+    };
+    let frame = frame_render::render(&char_rom, &screen)?
+        .scale(args.scale.max(1));
+    let png = frame.png_indexed(&frame_render::palette(args.port))?;
+    std::fs::write(&args.output, png)
+        .with_context(|| format!("Could not write {}", args.output.display()))
+}
+
+fn vram(path: &Path, port: usize) -> Result<Vec<u8>> {
+    if path.extension().is_some_and(|extension| extension == "bin") {
+        let vram = std::fs::read(path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        if vram.len() != VRAM_ROWS * VRAM_COLUMNS {
+            bail!("{} is {} bytes, expecting {}", path.display(), vram.len(), VRAM_ROWS * VRAM_COLUMNS);
+        }
+        return Ok(vram);
+    }
+    // host names from the hosts.txt beside the image
+    let hosts = match path.parent().map(|directory| directory.join("hosts.txt")) {
+        Some(hosts) if hosts.exists() => vram_gen::screen::hosts(&read(&hosts)?, vram_gen::PORTS)
+            .with_context(|| format!("{}", hosts.display()))?,
+        _ => vram_gen::screen::hosts("", vram_gen::PORTS)?,
+    };
+    let Some(host) = hosts.get(port) else {
+        bail!("Port {port}, expecting 0 to {}", vram_gen::PORTS - 1);
+    };
+    let placeholders = Placeholders::from_repository(Path::new(ROOT))?.for_port(port, host);
+    let memory = vram_gen::memory(&read(path)?, &placeholders)?;
+    Ok((0..VRAM_ROWS * VRAM_COLUMNS)
+        .map(|address| memory.get(address) as u8)
+        .collect())
+}
+
+fn read(path: &Path) -> Result<String> {
+    std::fs::read_to_string(path)
+        .with_context(|| format!("Could not read {}", path.display()))
+}
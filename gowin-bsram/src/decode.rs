@@ -0,0 +1,336 @@
+//! Read back the content of a memory emitted by
+//! [`Memory::emit`](crate::memory::Memory::emit) from the Verilog.
+//!
+//! The `INIT_RAM_xx` parameters of each block are decoded, then every
+//! address is resolved as the hardware does: the `CE` select and `AD`
+//! bits of the blocks, the registered select of the output multiplexers
+//! and the part selects of the `assign` to the output.
+
+use anyhow::{
+    Context,
+    Result,
+    bail,
+};
+use std::collections::HashMap;
+
+use crate::{
+    Block,
+    Primitive,
+};
+
+/// A block instance as parsed, parameters and ports by name.
+struct Parsed {
+    primitive: Primitive,
+    parameters: HashMap<String, String>,
+    ports: HashMap<String, String>,
+}
+
+/// A block with its read port connections.
+struct Decoded {
+    block: Block,
+    /// Address bit driving each `AD` bit, from bit 0 up, `None` if tied
+    ad: Vec<Option<usize>>,
+    /// Address bits `lsb .. msb` and the value enabling the block
+    select: Option<(usize, usize, usize)>,
+}
+
+/// Data bits `lsb .. lsb + width` of the output, driven by bits `from ..`
+/// of a block output selected by the registered address bits.
+struct Assign {
+    lsb: usize,
+    width: usize,
+    from: usize,
+    /// Address bits `lsb .. msb` registered by the select, or `None` for a
+    /// single block
+    select: Option<(usize, usize)>,
+    /// Block instance by select value
+    blocks: HashMap<usize, String>,
+}
+
+/// The value read at each of the `1 << address_width` addresses of memory
+/// `name` in `verilog`, read through `address` onto `q`, zero where no
+/// block drives the output.
+pub fn decode(verilog: &str, name: &str, address: &str, q: &str, address_width: usize) -> Result<Vec<u64>> {
+    let instances = parse_instances(verilog)?;
+    let prefix = format!("{name}_");
+    let mut blocks = HashMap::new();
+    for (instance, parsed) in &instances {
+        if instance.starts_with(&prefix) {
+            let block = decode_block(parsed, address)
+                .with_context(|| format!("Block {instance}"))?;
+            blocks.insert(instance.clone(), block);
+        }
+    }
+    if blocks.is_empty() {
+        bail!("No {name}_* block instances, expecting Gowin primitives");
+    }
+    let selects = parse_selects(verilog, &prefix, address)?;
+    let assigns = parse_assigns(verilog, &prefix, q, &selects)?;
+    if assigns.is_empty() {
+        bail!("No assign of {q} from the {name} blocks");
+    }
+
+    let mut content = vec![0; 1 << address_width];
+    for (addr, value) in content.iter_mut().enumerate() {
+        for assign in &assigns {
+            let number = match assign.select {
+                Some((lsb, msb)) => field(addr, lsb, msb),
+                None => 0,
+            };
+            let Some(instance) = assign.blocks.get(&number) else {
+                continue;
+            };
+            let Some(decoded) = blocks.get(instance) else {
+                bail!("Output of {instance} assigned to {q}, no such block");
+            };
+            if let Some((lsb, msb, enabled)) = decoded.select
+                && field(addr, lsb, msb) != enabled {
+                continue;
+            }
+            let ad = decoded.ad.iter()
+                .enumerate()
+                .filter(|(_, bit)| bit.is_some_and(|bit| (addr >> bit) & 1 != 0))
+                .fold(0, |ad, (position, _)| ad | (1 << position));
+            let entry = ad >> decoded.block.address_padding();
+            let data = (decoded.block.get(entry) >> assign.from) & ((1 << assign.width) - 1);
+            *value |= data << assign.lsb;
+        }
+    }
+    Ok(content)
+}
+
+/// Bits `lsb .. msb` of `value`.
+fn field(value: usize, lsb: usize, msb: usize) -> usize {
+    (value >> lsb) & ((1 << (msb - lsb)) - 1)
+}
+
+/// The `pROM` and `SDPB` instances by name, including the `X9` variants.
+fn parse_instances(verilog: &str) -> Result<Vec<(String, Parsed)>> {
+    let mut instances = Vec::new();
+    let mut lines = verilog.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        let primitive = match line {
+            "pROM" | "pROMX9" => Primitive::Prom,
+            "SDPB" | "SDPX9B" => Primitive::Sdpb,
+            _ => continue,
+        };
+        if lines.next() != Some("#(") {
+            bail!("{line} without parameters");
+        }
+        let parameters = connections(&mut lines, ")")?;
+        let Some(instance) = lines.next() else {
+            bail!("{line} without instance name");
+        };
+        if lines.next() != Some("(") {
+            bail!("{line} {instance} without ports");
+        }
+        let ports = connections(&mut lines, ");")?;
+        instances.push((instance.to_owned(), Parsed {
+            primitive,
+            parameters,
+            ports,
+        }));
+    }
+    Ok(instances)
+}
+
+/// The `.NAME(value)` lines up to `end`, skipping comments and blank lines.
+fn connections<'a>(lines: &mut impl Iterator<Item = &'a str>, end: &str) -> Result<HashMap<String, String>> {
+    let mut connections = HashMap::new();
+    for line in lines.by_ref() {
+        if line == end {
+            return Ok(connections);
+        }
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+        let line = line.trim_end_matches(',');
+        let Some((name, value)) = line.strip_prefix('.')
+            .and_then(|line| line.strip_suffix(')'))
+            .and_then(|line| line.split_once('(')) else {
+            bail!("Expecting '.NAME(value)', found '{line}'");
+        };
+        connections.insert(name.to_owned(), value.to_owned());
+    }
+    bail!("Missing '{end}'");
+}
+
+fn decode_block(parsed: &Parsed, address: &str) -> Result<Decoded> {
+    // the read port of an SDPB is port B
+    let (width, ad, ce) = match parsed.primitive {
+        Primitive::Prom => ("BIT_WIDTH", "AD", "CE"),
+        _ => ("BIT_WIDTH_1", "ADB", "CEB"),
+    };
+    let bit_width = connection(&parsed.parameters, width)?.parse()
+        .with_context(|| format!("Invalid {width}"))?;
+    let mut block = Block::new(parsed.primitive, bit_width)?;
+    for (parameter, value) in &parsed.parameters {
+        let Some(index) = parameter.strip_prefix("INIT_RAM_") else {
+            continue;
+        };
+        let index = usize::from_str_radix(index, 16)
+            .with_context(|| format!("Invalid {parameter}"))?;
+        let Some((_, digits)) = value.split_once("'h") else {
+            bail!("{parameter} is not hex");
+        };
+        block.set_init_ram(index, digits)?;
+    }
+
+    // AD bits from the most significant down
+    let mut bits = Vec::new();
+    let concatenation = connection(&parsed.ports, ad)?;
+    let concatenation = concatenation.strip_prefix('{')
+        .and_then(|parts| parts.strip_suffix('}'))
+        .unwrap_or(concatenation);
+    for part in concatenation.split(',') {
+        match part.split_once("'b") {
+            Some((count, _)) => {
+                let count: usize = count.parse()
+                    .with_context(|| format!("Invalid {ad} part '{part}'"))?;
+                bits.extend(std::iter::repeat_n(None, count));
+            }
+            None => {
+                let (lsb, msb) = part_select(part, address)
+                    .with_context(|| format!("Invalid {ad} part '{part}'"))?;
+                bits.extend((lsb..msb).rev().map(Some));
+            }
+        }
+    }
+    if bits.len() != crate::ADDRESS_WIDTH {
+        bail!("{ad} is {} bits wide", bits.len());
+    }
+    bits.reverse();
+
+    let ce = connection(&parsed.ports, ce)?;
+    let select = match ce.split_once(" & (") {
+        None => None,
+        Some((_, compare)) => {
+            let Some((bits, number)) = compare.strip_suffix(')')
+                .and_then(|compare| compare.split_once(" == ")) else {
+                bail!("Invalid CE '{ce}'");
+            };
+            let (lsb, msb) = part_select(bits, address)
+                .with_context(|| format!("Invalid CE '{ce}'"))?;
+            let number = number.parse()
+                .with_context(|| format!("Invalid CE '{ce}'"))?;
+            Some((lsb, msb, number))
+        }
+    };
+    Ok(Decoded {
+        block,
+        ad: bits,
+        select,
+    })
+}
+
+/// The value of parameter or port `name`.
+fn connection<'a>(connections: &'a HashMap<String, String>, name: &str) -> Result<&'a str> {
+    connections.get(name)
+        .map(String::as_str)
+        .with_context(|| format!("No {name}"))
+}
+
+/// Bits `lsb .. msb` of a part select `name[msb-1:lsb]` or `name[bit]`.
+fn part_select(part: &str, name: &str) -> Result<(usize, usize)> {
+    let Some(range) = part.trim()
+        .strip_prefix(name)
+        .and_then(|part| part.strip_prefix('['))
+        .and_then(|part| part.strip_suffix(']')) else {
+        bail!("Expecting a part select of {name}, found '{part}'");
+    };
+    let (msb, lsb) = range.split_once(':').unwrap_or((range, range));
+    let msb: usize = msb.parse()?;
+    let lsb: usize = lsb.parse()?;
+    if msb < lsb {
+        bail!("Descending part select '{part}'");
+    }
+    Ok((lsb, msb + 1))
+}
+
+/// The registered address bits of each select register, `{select} <=
+/// {address}[msb:lsb];`.
+fn parse_selects(verilog: &str, prefix: &str, address: &str) -> Result<HashMap<String, (usize, usize)>> {
+    let mut selects = HashMap::new();
+    for line in verilog.lines().map(str::trim) {
+        let Some((select, bits)) = line.strip_suffix(';')
+            .and_then(|line| line.split_once(" <= ")) else {
+            continue;
+        };
+        if select.starts_with(prefix) && select.ends_with("_select") {
+            selects.insert(select.to_owned(), part_select(bits, address)?);
+        }
+    }
+    Ok(selects)
+}
+
+/// The `assign {q}[msb:lsb] = ...;` statements, either a block output or a
+/// multiplexer of block outputs ending in zero.
+fn parse_assigns(verilog: &str, prefix: &str, q: &str, selects: &HashMap<String, (usize, usize)>) -> Result<Vec<Assign>> {
+    let mut assigns = Vec::new();
+    let target = format!("assign {q}[");
+    for statement in verilog.split(';').map(str::trim) {
+        if !statement.starts_with(&target) {
+            continue;
+        }
+        let Some((left, right)) = statement.strip_prefix("assign ")
+            .and_then(|statement| statement.split_once(" =")) else {
+            continue;
+        };
+        let (lsb, msb) = part_select(left, q)?;
+        let arms: Vec<&str> = right.trim().lines().map(str::trim).collect();
+        let mut assign = Assign {
+            lsb,
+            width: msb - lsb,
+            from: 0,
+            select: None,
+            blocks: HashMap::new(),
+        };
+        if let [single] = arms[..] {
+            let (instance, from) = block_output(single, assign.width)
+                .with_context(|| format!("Assign of {left}"))?;
+            assign.from = from;
+            assign.blocks.insert(0, instance);
+        } else {
+            let mut register = None;
+            for arm in &arms[..arms.len() - 1] {
+                let Some((select, number, value)) = arm.strip_prefix('(')
+                    .and_then(|arm| arm.split_once(" == "))
+                    .and_then(|(select, arm)| arm.split_once(") ? ").map(|(number, value)| (select, number, value))) else {
+                    bail!("Invalid multiplexer arm '{arm}' of {left}");
+                };
+                let number: usize = number.parse()
+                    .with_context(|| format!("Invalid multiplexer arm '{arm}' of {left}"))?;
+                let value = value.strip_suffix(" :").unwrap_or(value);
+                let (instance, from) = block_output(value, assign.width)
+                    .with_context(|| format!("Assign of {left}"))?;
+                assign.from = from;
+                assign.blocks.insert(number, instance);
+                register = Some(select);
+            }
+            let Some(register) = register else {
+                bail!("Empty multiplexer of {left}");
+            };
+            let Some(&select) = selects.get(register) else {
+                bail!("No select register {register} of {left}");
+            };
+            assign.select = Some(select);
+        }
+        if assign.blocks.values().all(|instance| instance.starts_with(prefix)) {
+            assigns.push(assign);
+        }
+    }
+    Ok(assigns)
+}
+
+/// The instance and lowest bit of a `{instance}_q[msb:lsb]` block output
+/// `width` bits wide.
+fn block_output(expression: &str, width: usize) -> Result<(String, usize)> {
+    let Some((instance, bits)) = expression.split_once("_q[") else {
+        bail!("Expecting a block output, found '{expression}'");
+    };
+    let (lsb, msb) = part_select(&format!("q[{bits}"), "q")?;
+    if msb - lsb != width {
+        bail!("{instance} output is {} bits, expecting {width}", msb - lsb);
+    }
+    Ok((instance.to_owned(), lsb))
+}
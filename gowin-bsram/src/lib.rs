@@ -0,0 +1,347 @@
+//! Gowin GW1N block SRAM primitives and their Verilog instantiation.
+//!
+//! A block holds 16Kbit, or 18Kbit in the 9/18/36 bit wide configurations
+//! of the `X9` primitives `pROMX9`, `SDPX9B` and `DPX9B`.
+//! Content is set per entry, entry `i` occupying bits `i * bit_width` and up
+//! of the block, and emitted as the `INIT_RAM_00` to `INIT_RAM_3F`
+//! parameters.
+
+use anyhow::{
+    Result,
+    bail,
+};
+
+pub mod decode;
+pub mod memory;
+
+/// Width of the `AD` address ports, in bits.
+pub const ADDRESS_WIDTH: usize = 14;
+
+/// Number of `INIT_RAM_xx` parameters.
+const INIT_RAMS: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Primitive {
+    /// Single port ROM
+    Prom,
+    /// Semi dual port, write on port A and read on port B
+    Sdpb,
+    /// True dual port
+    Dpb,
+}
+
+impl Primitive {
+    pub fn name(self) -> &'static str {
+        match self {
+            Primitive::Prom => "pROM",
+            Primitive::Sdpb => "SDPB",
+            Primitive::Dpb => "DPB",
+        }
+    }
+
+    /// Name of the `X9` variant, required for the 9/18/36 bit wide
+    /// configurations.
+    pub fn x9_name(self) -> &'static str {
+        match self {
+            Primitive::Prom => "pROMX9",
+            Primitive::Sdpb => "SDPX9B",
+            Primitive::Dpb => "DPX9B",
+        }
+    }
+
+    /// Supported `BIT_WIDTH` configurations, 9/18/36 with the `X9` variant.
+    pub fn bit_widths(self) -> &'static [usize] {
+        match self {
+            Primitive::Prom | Primitive::Sdpb => &[1, 2, 4, 8, 9, 16, 18, 32, 36],
+            Primitive::Dpb => &[1, 2, 4, 8, 9, 16, 18],
+        }
+    }
+
+    /// Ports that must be connected.
+    fn ports(self) -> &'static [&'static str] {
+        match self {
+            Primitive::Prom => &[
+                "DO", "CLK", "OCE", "CE", "RESET", "AD",
+            ],
+            Primitive::Sdpb => &[
+                "CLKA", "CEA", "RESETA", "BLKSELA", "ADA", "DI",
+                "CLKB", "CEB", "RESETB", "OCE", "BLKSELB", "ADB", "DO",
+            ],
+            Primitive::Dpb => &[
+                "CLKA", "CEA", "OCEA", "RESETA", "WREA", "BLKSELA", "ADA", "DIA", "DOA",
+                "CLKB", "CEB", "OCEB", "RESETB", "WREB", "BLKSELB", "ADB", "DIB", "DOB",
+            ],
+        }
+    }
+}
+
+/// One BSRAM block configured as `primitive` with `bit_width` wide entries.
+#[derive(Clone)]
+pub struct Block {
+    primitive: Primitive,
+    bit_width: usize,
+    init: Vec<u64>,
+    /// One past the highest entry set, `INIT_RAM` beyond are not emitted.
+    used: usize,
+}
+
+impl Block {
+    pub fn new(primitive: Primitive, bit_width: usize) -> Result<Self> {
+        if !primitive.bit_widths().contains(&bit_width) {
+            bail!("{} does not support BIT_WIDTH {bit_width}", primitive.name());
+        }
+        let mut block = Block {
+            primitive,
+            bit_width,
+            init: Vec::new(),
+            used: 0,
+        };
+        block.init = vec![0; block.depth()];
+        Ok(block)
+    }
+
+    pub fn primitive(&self) -> Primitive {
+        self.primitive
+    }
+
+    pub fn bit_width(&self) -> usize {
+        self.bit_width
+    }
+
+    /// Name of the primitive instantiated, the `X9` variant in the 9/18/36
+    /// bit wide configurations.
+    pub fn name(&self) -> &'static str {
+        if is_x9(self.bit_width) {
+            self.primitive.x9_name()
+        } else {
+            self.primitive.name()
+        }
+    }
+
+    /// Capacity in bits, 18Kbit for the 9/18/36 bit wide configurations.
+    pub fn capacity(&self) -> usize {
+        capacity(self.bit_width)
+    }
+
+    /// Number of entries.
+    pub fn depth(&self) -> usize {
+        self.capacity() / self.bit_width
+    }
+
+    /// Width of the entry address, the upper bits of `AD`.
+    pub fn address_width(&self) -> usize {
+        self.depth().ilog2() as usize
+    }
+
+    /// Low bits of `AD` below the entry address, tied to zero for a ROM.
+    pub fn address_padding(&self) -> usize {
+        ADDRESS_WIDTH - self.address_width()
+    }
+
+    pub fn set(&mut self, address: usize, value: u64) -> Result<()> {
+        if address >= self.depth() {
+            bail!("Address {address} beyond depth {} of {} x {}",
+                self.depth(), self.name(), self.bit_width);
+        }
+        if value >> self.bit_width != 0 {
+            bail!("Value {value:#x} wider than {} bits", self.bit_width);
+        }
+        self.init[address] = value;
+        self.used = self.used.max(address + 1);
+        Ok(())
+    }
+
+    pub fn get(&self, address: usize) -> u64 {
+        self.init[address]
+    }
+
+    /// Bits per `INIT_RAM_xx` parameter.
+    fn init_ram_bits(&self) -> usize {
+        self.capacity() / INIT_RAMS
+    }
+
+    fn bit(&self, bit: usize) -> u64 {
+        (self.init[bit / self.bit_width] >> (bit % self.bit_width)) & 1
+    }
+
+    /// The `INIT_RAM_xx` parameters covering the entries set, each without
+    /// the trailing comma or new line.
+    pub fn init_rams(&self) -> Vec<String> {
+        let bits = self.init_ram_bits();
+        let count = (self.used * self.bit_width).div_ceil(bits);
+        let mut init_rams = Vec::with_capacity(count);
+        for index in 0..count {
+            let mut line = format!("        .INIT_RAM_{index:02X}({bits}'h");
+            line.reserve(bits / 4 + 2);
+            for nibble in (0..(bits / 4)).rev() {
+                let bit = (index * bits) + (nibble * 4);
+                let digit = (self.bit(bit + 3) << 3)
+                    | (self.bit(bit + 2) << 2)
+                    | (self.bit(bit + 1) << 1)
+                    | self.bit(bit);
+                line.push(char::from_digit(digit as u32, 16).expect("hex"));
+            }
+            line.push(')');
+            init_rams.push(line);
+        }
+        init_rams
+    }
+
+    /// Set the entries covered by `INIT_RAM_{index}` from the hex digits of
+    /// its value, the inverse of [`Block::init_rams`].
+    pub fn set_init_ram(&mut self, index: usize, digits: &str) -> Result<()> {
+        let bits = self.init_ram_bits();
+        if index >= INIT_RAMS {
+            bail!("No INIT_RAM_{index:02X}");
+        }
+        if digits.len() != bits / 4 {
+            bail!("INIT_RAM_{index:02X} has {} hex digits, expecting {}", digits.len(), bits / 4);
+        }
+        for (nibble, digit) in digits.chars().rev().enumerate() {
+            let Some(digit) = digit.to_digit(16) else {
+                bail!("INIT_RAM_{index:02X} has invalid hex digit '{digit}'");
+            };
+            for offset in 0..4 {
+                let bit = (index * bits) + (nibble * 4) + offset;
+                let entry = bit / self.bit_width;
+                self.init[entry] |= u64::from((digit >> offset) & 1) << (bit % self.bit_width);
+                if digit != 0 {
+                    self.used = self.used.max(entry + 1);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Instantiate as `name`, connect the ports with [`Instance::port`].
+    pub fn instance(&self, name: &str) -> Instance<'_> {
+        Instance {
+            block: self,
+            name: name.to_owned(),
+            ports: Vec::new(),
+        }
+    }
+
+    fn parameters(&self) -> Vec<String> {
+        let width = self.bit_width;
+        let mut parameters = Vec::new();
+        match self.primitive {
+            Primitive::Prom => {
+                parameters.push("        .READ_MODE(1'b0)".to_owned());
+                parameters.push(format!("        .BIT_WIDTH({width})"));
+            }
+            Primitive::Sdpb => {
+                parameters.push("        .READ_MODE(1'b0)".to_owned());
+                parameters.push(format!("        .BIT_WIDTH_0({width})"));
+                parameters.push(format!("        .BIT_WIDTH_1({width})"));
+                parameters.push("        .BLK_SEL_0(3'b000)".to_owned());
+                parameters.push("        .BLK_SEL_1(3'b000)".to_owned());
+            }
+            Primitive::Dpb => {
+                parameters.push("        .READ_MODE0(1'b0)".to_owned());
+                parameters.push("        .READ_MODE1(1'b0)".to_owned());
+                parameters.push("        .WRITE_MODE0(2'b00)".to_owned());
+                parameters.push("        .WRITE_MODE1(2'b00)".to_owned());
+                parameters.push(format!("        .BIT_WIDTH_0({width})"));
+                parameters.push(format!("        .BIT_WIDTH_1({width})"));
+                parameters.push("        .BLK_SEL_0(3'b000)".to_owned());
+                parameters.push("        .BLK_SEL_1(3'b000)".to_owned());
+            }
+        }
+        parameters.push("        .RESET_MODE(\"SYNC\")".to_owned());
+        parameters.extend(self.init_rams());
+        parameters
+    }
+}
+
+/// Whether `bit_width` is a configuration of the `X9` primitives.
+fn is_x9(bit_width: usize) -> bool {
+    bit_width.is_multiple_of(9)
+}
+
+/// Capacity in bits of a block configured `bit_width` wide.
+pub fn capacity(bit_width: usize) -> usize {
+    if is_x9(bit_width) {
+        18 * 1024
+    } else {
+        16 * 1024
+    }
+}
+
+enum Port {
+    Connect(String, String),
+    Comment(String),
+    Blank,
+}
+
+/// Verilog instantiation of a [`Block`].
+pub struct Instance<'a> {
+    block: &'a Block,
+    name: String,
+    ports: Vec<Port>,
+}
+
+impl Instance<'_> {
+    pub fn port(mut self, port: &str, expression: &str) -> Self {
+        self.ports.push(Port::Connect(port.to_owned(), expression.to_owned()));
+        self
+    }
+
+    pub fn comment(mut self, comment: &str) -> Self {
+        self.ports.push(Port::Comment(comment.to_owned()));
+        self
+    }
+
+    pub fn blank(mut self) -> Self {
+        self.ports.push(Port::Blank);
+        self
+    }
+
+    /// Append the instantiation, failing on missing or unknown ports.
+    pub fn emit(&self, verilog: &mut String) -> Result<()> {
+        let primitive = self.block.primitive;
+        let name = self.block.name();
+        let connected: Vec<&str> = self.ports.iter()
+            .filter_map(|port| match port {
+                Port::Connect(port, _) => Some(port.as_str()),
+                _ => None,
+            })
+            .collect();
+        for port in primitive.ports() {
+            if !connected.contains(port) {
+                bail!("{name} {} port {port} not connected", self.name);
+            }
+        }
+        for port in &connected {
+            if !primitive.ports().contains(port) {
+                bail!("{name} {} has no port {port}", self.name);
+            }
+        }
+
+        verilog.push_str(&format!("    {name}\n"));
+        verilog.push_str("    #(\n");
+        verilog.push_str(&self.block.parameters().join(",\n"));
+        verilog.push_str("\n");
+        verilog.push_str("    )\n");
+        verilog.push_str(&format!("    {}\n", self.name));
+        verilog.push_str("    (\n");
+        let mut remaining = connected.len();
+        for port in &self.ports {
+            match port {
+                Port::Connect(port, expression) => {
+                    remaining -= 1;
+                    let comma = if remaining > 0 { "," } else { "" };
+                    verilog.push_str(&format!("        .{port}({expression}){comma}\n"));
+                }
+                Port::Comment(comment) => {
+                    verilog.push_str(&format!("        // {comment}\n"));
+                }
+                Port::Blank => {
+                    verilog.push_str("\n");
+                }
+            }
+        }
+        verilog.push_str("    );\n");
+        Ok(())
+    }
+}
@@ -0,0 +1,531 @@
+//! Memories wider or deeper than a single block.
+//!
+//! The data bits are split into slices, each slice stored in one or more
+//! blocks of the same `BIT_WIDTH` holding consecutive ranges of entries.
+//! The upper address bits select the block of a slice, registered so that
+//! the selection lines up with the block output.
+//!
+//! For simulators without the Gowin primitive models a memory can instead
+//! be emitted as a behavioural array loaded by `$readmemh`, see [`Style`].
+
+use anyhow::{
+    Result,
+    bail,
+};
+
+use crate::{
+    Block,
+    Primitive,
+};
+
+/// How the bits of a `depth` x `width` memory are split over blocks.
+#[derive(Clone, Debug)]
+pub struct Layout {
+    pub primitive: Primitive,
+    pub depth: usize,
+    pub width: usize,
+    /// Slices from the least significant bit up.
+    pub slices: Vec<Slice>,
+}
+
+/// Data bits `lsb .. lsb + width` stored in blocks configured `bit_width`
+/// wide.
+#[derive(Clone, Copy, Debug)]
+pub struct Slice {
+    pub lsb: usize,
+    pub width: usize,
+    pub bit_width: usize,
+}
+
+/// Ranking of a layout, lowest first: fewest blocks, then fewest wasted
+/// bits, then the shallowest output multiplexer, then fewest slices.
+type Cost = (usize, usize, usize, usize);
+
+impl Layout {
+    /// Plan the layout of `depth` entries of `width` bits using the fewest
+    /// `primitive` blocks, searching all `BIT_WIDTH` configurations.
+    pub fn plan(primitive: Primitive, depth: usize, width: usize) -> Result<Self> {
+        if primitive == Primitive::Dpb {
+            bail!("Memories of {} blocks are not supported", primitive.name());
+        }
+        if depth == 0 || width == 0 {
+            bail!("Empty memory {depth} x {width}");
+        }
+        let deepest = primitive.bit_widths().iter()
+            .map(|&bit_width| Slice { lsb: 0, width: bit_width, bit_width }.blocks(depth))
+            .max()
+            .expect("bit widths");
+        // an exact search for each limit on the blocks per slice, since the
+        // multiplexer depth does not add up over slices
+        let mut best: Option<(Cost, Vec<Slice>)> = None;
+        for limit in 1..=deepest {
+            let Some((blocks, waste, slices)) = Self::search(primitive, depth, width, limit) else {
+                continue;
+            };
+            let deepest = slices.iter()
+                .map(|slice| slice.blocks(depth))
+                .max()
+                .unwrap_or(0);
+            let cost = (blocks, waste, deepest, slices.len());
+            if best.as_ref().is_none_or(|(best, _)| cost < *best) {
+                best = Some((cost, slices));
+            }
+        }
+        let Some((_, slices)) = best else {
+            bail!("No layout for {depth} x {width}");
+        };
+        Ok(Layout {
+            primitive,
+            depth,
+            width,
+            slices,
+        })
+    }
+
+    /// Cheapest slices by (blocks, waste, slices) with at most `limit`
+    /// blocks per slice, by dynamic programming over the remaining width.
+    fn search(primitive: Primitive, depth: usize, width: usize, limit: usize) -> Option<(usize, usize, Vec<Slice>)> {
+        // best[remaining] = (blocks, waste, slice count, bit width taken first)
+        let mut best: Vec<Option<(usize, usize, usize, usize)>> = vec![None; width + 1];
+        best[0] = Some((0, 0, 0, 0));
+        for remaining in 1..=width {
+            // widest first, so that ties put the wide slices in the low bits
+            for &bit_width in primitive.bit_widths().iter().rev() {
+                let slice = Slice {
+                    lsb: 0,
+                    width: bit_width.min(remaining),
+                    bit_width,
+                };
+                if slice.blocks(depth) > limit {
+                    continue;
+                }
+                let Some((blocks, waste, count, _)) = best[remaining - slice.width] else {
+                    continue;
+                };
+                let cost = (blocks + slice.blocks(depth), waste + slice.waste(depth), count + 1, bit_width);
+                if best[remaining].is_none_or(|best| (cost.0, cost.1, cost.2) < (best.0, best.1, best.2)) {
+                    best[remaining] = Some(cost);
+                }
+            }
+        }
+        let (blocks, waste, _, _) = best[width]?;
+        let mut slices = Vec::new();
+        let mut lsb = 0;
+        while lsb < width {
+            let (_, _, _, bit_width) = best[width - lsb].expect("planned");
+            let slice_width = bit_width.min(width - lsb);
+            slices.push(Slice {
+                lsb,
+                width: slice_width,
+                bit_width,
+            });
+            lsb += slice_width;
+        }
+        Some((blocks, waste, slices))
+    }
+
+    /// Total number of blocks.
+    pub fn blocks(&self) -> usize {
+        self.slices.iter()
+            .map(|slice| slice.blocks(self.depth))
+            .sum()
+    }
+
+    /// Bits of the blocks not holding data.
+    pub fn waste(&self) -> usize {
+        self.slices.iter()
+            .map(|slice| slice.waste(self.depth))
+            .sum()
+    }
+
+    /// Human readable utilization, one line per slice after a summary.
+    pub fn report(&self) -> String {
+        let bits = self.depth * self.width;
+        let total = bits + self.waste();
+        let mut report = format!(
+            "{} x {} bits in {} {} block{}, {bits} of {total} bits used, {} wasted ({:.1}%)\n",
+            self.depth, self.width, self.blocks(), self.primitive.name(),
+            if self.blocks() == 1 { "" } else { "s" },
+            self.waste(), (self.waste() * 100) as f64 / total as f64,
+        );
+        for slice in &self.slices {
+            report.push_str(&format!(
+                "  bits {:>5}  {} x {} x {} bit, {} wasted\n",
+                bits_range(slice.lsb + slice.width, slice.lsb),
+                slice.blocks(self.depth), slice.block_depth(), slice.bit_width,
+                slice.waste(self.depth),
+            ));
+        }
+        report
+    }
+}
+
+impl Slice {
+    /// Entries per block.
+    pub fn block_depth(&self) -> usize {
+        crate::capacity(self.bit_width) / self.bit_width
+    }
+
+    /// Number of blocks for `depth` entries.
+    pub fn blocks(&self, depth: usize) -> usize {
+        depth.div_ceil(self.block_depth())
+    }
+
+    /// Bits of the blocks for `depth` entries not holding data.
+    pub fn waste(&self, depth: usize) -> usize {
+        (self.blocks(depth) * crate::capacity(self.bit_width)) - (depth * self.width)
+    }
+}
+
+/// The read side of a memory, names of wires declared by the caller.
+pub struct Read<'a> {
+    pub clk: &'a str,
+    pub ce: &'a str,
+    pub address: &'a str,
+    pub q: &'a str,
+}
+
+/// The write side of a memory, names of wires declared by the caller.
+pub struct Write<'a> {
+    pub clk: &'a str,
+    pub ce: &'a str,
+    pub address: &'a str,
+    pub data: &'a str,
+}
+
+/// How [`Memory::emit`] implements a memory.
+#[derive(Clone, Debug, Default)]
+pub enum Style {
+    /// Gowin BSRAM primitives with the content in `INIT_RAM_xx` parameters
+    #[default]
+    Gowin,
+    /// A behavioural array with the same one cycle read latency, loaded by
+    /// `$readmemh` from `file`, the default of an `{NAME}_INIT_FILE`
+    /// parameter; see [`Memory::hex`]
+    Readmemh { file: String },
+    /// As [`Style::Readmemh`], loaded from the file named by `parameter`,
+    /// which the enclosing module declares itself. Parameters declared in
+    /// the body of a module with a parameter port list, or in a generate
+    /// block, are local and cannot be overridden.
+    ReadmemhParameter { parameter: String },
+}
+
+/// Memory of `layout.depth` entries addressed by `address_width` bits,
+/// with initial content. Entries at and beyond the depth read as zero.
+pub struct Memory {
+    layout: Layout,
+    address_width: usize,
+    content: Vec<u64>,
+}
+
+impl Memory {
+    pub fn new(layout: Layout, address_width: usize) -> Result<Self> {
+        if layout.depth > (1 << address_width) {
+            bail!("Depth {} does not fit in {address_width} address bits", layout.depth);
+        }
+        if layout.width > 64 {
+            bail!("Width {} wider than 64 bits", layout.width);
+        }
+        let mut covered = 0;
+        for slice in &layout.slices {
+            if slice.lsb != covered || slice.width > slice.bit_width {
+                bail!("Layout slice {slice:?} does not follow bit {covered}");
+            }
+            covered += slice.width;
+        }
+        if covered != layout.width {
+            bail!("Layout covers {covered} bits of {}", layout.width);
+        }
+        Ok(Memory {
+            content: vec![0; layout.depth],
+            layout,
+            address_width,
+        })
+    }
+
+    pub fn set(&mut self, address: usize, value: u64) -> Result<()> {
+        if address >= self.layout.depth {
+            bail!("Address {address} beyond depth {}", self.layout.depth);
+        }
+        if value >> self.layout.width != 0 {
+            bail!("Value {value:#x} wider than {} bits", self.layout.width);
+        }
+        self.content[address] = value;
+        Ok(())
+    }
+
+    /// The entry at `address`, zero at and beyond the depth.
+    pub fn get(&self, address: usize) -> u64 {
+        self.content.get(address).copied().unwrap_or(0)
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    /// The content in `$readmemh` format, one hex entry per line.
+    pub fn hex(&self) -> String {
+        let digits = self.layout.width.div_ceil(4);
+        let mut hex = String::with_capacity(self.content.len() * (digits + 1));
+        for value in &self.content {
+            hex.push_str(&format!("{value:0digits$x}\n"));
+        }
+        hex
+    }
+
+    /// The content as a Gowin memory initialization file, as used by the
+    /// IP generator.
+    pub fn mi(&self) -> String {
+        let digits = self.layout.width.div_ceil(4);
+        let mut mi = String::with_capacity(self.content.len() * (digits + 1));
+        mi.push_str("#File_format=Hex\n");
+        mi.push_str(&format!("#Address_depth={}\n", self.layout.depth));
+        mi.push_str(&format!("#Data_width={}\n", self.layout.width));
+        for value in &self.content {
+            mi.push_str(&format!("{value:0digits$X}\n"));
+        }
+        mi
+    }
+
+    /// Append the memory in `style`. For [`Style::Gowin`] these are
+    /// declarations, block instances named `{name}_{slice}_{block}` and the
+    /// output multiplexers. A `pROM` layout has no `write` side, an `SDPB`
+    /// layout needs one.
+    pub fn emit(&self, name: &str, style: &Style, read: &Read, write: Option<&Write>, verilog: &mut String) -> Result<()> {
+        match (self.layout.primitive, write) {
+            (Primitive::Prom, None) | (Primitive::Sdpb, Some(_)) => {}
+            (primitive, _) => bail!("{} memory {name} with write port {}", primitive.name(), write.is_some()),
+        }
+        match style {
+            Style::Gowin => {}
+            Style::Readmemh { file } => {
+                let parameter = format!("{}_INIT_FILE", name.to_uppercase());
+                self.emit_readmemh(name, &parameter, Some(file), read, write, verilog);
+                return Ok(());
+            }
+            Style::ReadmemhParameter { parameter } => {
+                self.emit_readmemh(name, parameter, None, read, write, verilog);
+                return Ok(());
+            }
+        }
+        for (index, slice) in self.layout.slices.iter().enumerate() {
+            let shape = Block::new(self.layout.primitive, slice.bit_width)?;
+            let block_width = shape.address_width();
+            let used_width = block_width.min(self.address_width);
+            let block_address = |address: &str| {
+                let mut parts = Vec::new();
+                if block_width > used_width {
+                    parts.push(format!("{}'b0", block_width - used_width));
+                }
+                parts.push(bits(address, used_width, 0));
+                if shape.address_padding() > 0 {
+                    parts.push(format!("{}'b0", shape.address_padding()));
+                }
+                format!("{{{}}}", parts.join(","))
+            };
+            let blocks = slice.blocks(self.layout.depth);
+            // select by the address bits above those of the block
+            let select = if self.address_width > block_width {
+                let select = format!("{name}_{index}_select");
+                verilog.push_str(&format!("    reg [{}:0] {select};\n", self.address_width - block_width - 1));
+                verilog.push_str("\n");
+                verilog.push_str(&format!("    always @(posedge {}) begin\n", read.clk));
+                verilog.push_str(&format!("        if ({}) begin\n", read.ce));
+                verilog.push_str(&format!("            {select} <= {};\n", bits(read.address, self.address_width, block_width)));
+                verilog.push_str("        end\n");
+                verilog.push_str("    end\n");
+                verilog.push_str("\n");
+                Some(select)
+            } else {
+                None
+            };
+            let enable = |ce: &str, address: &str, number: usize| match &select {
+                Some(_) => format!("{ce} & ({} == {number})", bits(address, self.address_width, block_width)),
+                None => ce.to_owned(),
+            };
+            let do_width = if slice.bit_width.is_multiple_of(9) { 36 } else { 32 };
+            for number in 0..blocks {
+                verilog.push_str(&format!("    wire [{}:0] {name}_{index}_{number}_q;\n", do_width - 1));
+            }
+            verilog.push_str("\n");
+            for number in 0..blocks {
+                let first = number * slice.block_depth();
+                let mut block = Block::new(self.layout.primitive, slice.bit_width)?;
+                for entry in first..(first + slice.block_depth()).min(self.layout.depth) {
+                    let value = (self.content[entry] >> slice.lsb) & ((1 << slice.width) - 1);
+                    block.set(entry - first, value)?;
+                }
+                let instance = format!("{name}_{index}_{number}");
+                let output = format!("{name}_{index}_{number}_q");
+                match write {
+                    None => {
+                        block.instance(&instance)
+                            .port("DO", &output)
+                            .port("CLK", read.clk)
+                            .port("OCE", "1'b1")
+                            .port("CE", &enable(read.ce, read.address, number))
+                            .port("RESET", "1'b0")
+                            .port("AD", &block_address(read.address))
+                            .emit(verilog)?;
+                    }
+                    Some(write) => {
+                        let mut data = Vec::new();
+                        if do_width > slice.width {
+                            data.push(format!("{}'b0", do_width - slice.width));
+                        }
+                        data.push(bits(write.data, slice.lsb + slice.width, slice.lsb));
+                        block.instance(&instance)
+                            .comment("port A = write")
+                            .port("CLKA", write.clk)
+                            .port("CEA", &enable(write.ce, write.address, number))
+                            .port("RESETA", "1'b0")
+                            .port("BLKSELA", "3'b0")
+                            .port("ADA", &block_address(write.address))
+                            .port("DI", &format!("{{{}}}", data.join(",")))
+                            .blank()
+                            .comment("port B = read")
+                            .port("CLKB", read.clk)
+                            .port("CEB", &enable(read.ce, read.address, number))
+                            .port("RESETB", "1'b0")
+                            .port("OCE", "1'b1")
+                            .port("BLKSELB", "3'b0")
+                            .port("ADB", &block_address(read.address))
+                            .port("DO", &output)
+                            .emit(verilog)?;
+                    }
+                }
+                verilog.push_str("\n");
+            }
+            let target = bits(read.q, slice.lsb + slice.width, slice.lsb);
+            let output = |number: usize| bits(&format!("{name}_{index}_{number}_q"), slice.width, 0);
+            match &select {
+                None => {
+                    verilog.push_str(&format!("    assign {target} = {};\n", output(0)));
+                }
+                Some(select) => {
+                    verilog.push_str(&format!("    assign {target} =\n"));
+                    for number in 0..blocks {
+                        verilog.push_str(&format!("        ({select} == {number}) ? {} :\n", output(number)));
+                    }
+                    verilog.push_str(&format!("        {}'b0;\n", slice.width));
+                }
+            }
+            verilog.push_str("\n");
+        }
+        Ok(())
+    }
+
+    /// Behavioural array `{name}_data` read into the register `{name}_q`,
+    /// entries beyond the depth reading as zero like unused blocks, loaded
+    /// from the file named by `parameter`, declared with its default `file`
+    /// if given.
+    fn emit_readmemh(&self, name: &str, parameter: &str, file: Option<&str>, read: &Read, write: Option<&Write>, verilog: &mut String) {
+        let depth = self.layout.depth;
+        let width = self.layout.width;
+        let data = format!("{name}_data");
+        let output = format!("{name}_q");
+        // guard the index where the address reaches beyond the depth
+        let in_range = |address: &str| (depth < (1 << self.address_width))
+            .then(|| format!("({address} < {}'d{depth})", self.address_width));
+
+        verilog.push_str(&format!("    // behavioural {}, not a Gowin primitive\n",
+            if write.is_some() { "RAM" } else { "ROM" }));
+        if let Some(file) = file {
+            verilog.push_str(&format!("    parameter {parameter} = \"{file}\";\n"));
+            verilog.push_str("\n");
+        }
+        verilog.push_str(&format!("    reg [{}:0] {data} [0:{}];\n", width - 1, depth - 1));
+        verilog.push_str(&format!("    reg [{}:0] {output};\n", width - 1));
+        verilog.push_str("\n");
+        verilog.push_str("    initial begin\n");
+        verilog.push_str(&format!("        $readmemh({parameter}, {data});\n"));
+        verilog.push_str("    end\n");
+        verilog.push_str("\n");
+        if let Some(write) = write {
+            let enable = match in_range(write.address) {
+                Some(in_range) => format!("{} && {in_range}", write.ce),
+                None => write.ce.to_owned(),
+            };
+            verilog.push_str(&format!("    always @(posedge {}) begin\n", write.clk));
+            verilog.push_str(&format!("        if ({enable}) begin\n"));
+            verilog.push_str(&format!("            {data}[{}] <= {};\n", write.address, write.data));
+            verilog.push_str("        end\n");
+            verilog.push_str("    end\n");
+            verilog.push_str("\n");
+        }
+        let value = match in_range(read.address) {
+            Some(in_range) => format!("{in_range} ? {data}[{}] : {width}'b0", read.address),
+            None => format!("{data}[{}]", read.address),
+        };
+        verilog.push_str(&format!("    always @(posedge {}) begin\n", read.clk));
+        verilog.push_str(&format!("        if ({}) begin\n", read.ce));
+        verilog.push_str(&format!("            {output} <= {value};\n"));
+        verilog.push_str("        end\n");
+        verilog.push_str("    end\n");
+        verilog.push_str("\n");
+        verilog.push_str(&format!("    assign {} = {output};\n", read.q));
+        verilog.push_str("\n");
+    }
+}
+
+/// Verilog part select of bits `lsb .. msb` (exclusive) of `name`.
+pub fn bits(name: &str, msb: usize, lsb: usize) -> String {
+    format!("{name}[{}]", bits_range(msb, lsb))
+}
+
+/// Bit range `msb-1:lsb`, or the single bit.
+fn bits_range(msb: usize, lsb: usize) -> String {
+    if msb - lsb == 1 {
+        format!("{lsb}")
+    } else {
+        format!("{}:{lsb}", msb - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `depth` x `width` ROM of a pseudo random pattern, emitted and
+    /// decoded back.
+    fn round_trip(depth: usize, width: usize) -> (String, Memory, Vec<u64>) {
+        let layout = Layout::plan(Primitive::Prom, depth, width).unwrap();
+        let address_width = depth.next_power_of_two().ilog2() as usize;
+        let mut memory = Memory::new(layout, address_width).unwrap();
+        for address in 0..depth {
+            let value = (address as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) >> (64 - width);
+            memory.set(address, value).unwrap();
+        }
+        let read = Read {
+            clk: "clk",
+            ce: "ce",
+            address: "addr",
+            q: "q",
+        };
+        let mut verilog = String::new();
+        memory.emit("rom", &Style::Gowin, &read, None, &mut verilog).unwrap();
+        let decoded = crate::decode::decode(&verilog, "rom", "addr", "q", address_width).unwrap();
+        (verilog, memory, decoded)
+    }
+
+    #[test]
+    fn x9_primitive() {
+        let (verilog, memory, decoded) = round_trip(4096, 9);
+        assert!(memory.layout().slices.iter().any(|slice| slice.bit_width == 9));
+        assert!(verilog.contains("    pROMX9\n"));
+        assert!(!verilog.contains("    pROM\n"));
+        for (address, value) in decoded.iter().enumerate() {
+            assert_eq!(*value, memory.get(address), "address {address}");
+        }
+    }
+
+    #[test]
+    fn primitive() {
+        let (verilog, memory, decoded) = round_trip(2048, 8);
+        assert!(verilog.contains("    pROM\n"));
+        assert!(!verilog.contains("X9"));
+        for (address, value) in decoded.iter().enumerate() {
+            assert_eq!(*value, memory.get(address), "address {address}");
+        }
+    }
+}
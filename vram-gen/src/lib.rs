@@ -0,0 +1,190 @@
+use anyhow::{
+    Context,
+    Result,
+};
+use gowin_bsram::Primitive;
+use gowin_bsram::memory::{
+    Layout,
+    Memory,
+    Read,
+    Style,
+    Write,
+};
+
+use std::path::Path;
+
+pub mod screen;
+
+use screen::Placeholders;
+
+/// UART ports, each with its own power-on screen.
+pub const PORTS: usize = 4;
+
+/// BSRAM layout of the 32 rows of 128 columns of bytes.
+pub fn layout() -> Result<Layout> {
+    Layout::plan(Primitive::Sdpb, 32 * 128, 8)
+}
+
+/// The memory initialised with the screen in `init.txt` format, see
+/// [`screen`].
+pub fn memory(init: &str, placeholders: &Placeholders) -> Result<Memory> {
+    let vram = screen::parse(init, placeholders)?;
+
+    let mut memory = Memory::new(layout()?, 12)?;
+    for (addr, data) in vram.iter().enumerate() {
+        memory.set(addr, *data as u64)?;
+    }
+    Ok(memory)
+}
+
+/// The memory of each port, its screen read from `init-<port>.txt` in
+/// `directory` or otherwise the shared `init.txt`, and its `{HOST}` from
+/// `hosts.txt` if present. Ports are numbered from 0, as the `PORT`
+/// parameter.
+pub fn screens(directory: &Path, placeholders: &Placeholders) -> Result<Vec<Memory>> {
+    let path = directory.join("hosts.txt");
+    let hosts = match std::fs::read_to_string(&path) {
+        Ok(text) => screen::hosts(&text, PORTS)
+            .with_context(|| format!("{}", path.display()))?,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => screen::hosts("", PORTS)?,
+        Err(error) => return Err(error)
+            .with_context(|| format!("Could not read {}", path.display())),
+    };
+    let mut screens = Vec::with_capacity(PORTS);
+    for (port, host) in hosts.iter().enumerate() {
+        let mut path = directory.join(format!("init-{port}.txt"));
+        if !path.exists() {
+            path = directory.join("init.txt");
+        }
+        let init = std::fs::read_to_string(&path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        let memory = memory(&init, &placeholders.for_port(port, host))
+            .with_context(|| format!("{} for port {port}", path.display()))?;
+        screens.push(memory);
+    }
+    Ok(screens)
+}
+
+/// Generate the `vram` Verilog module, its `PORT` parameter selecting the
+/// power-on screen among `screens`, the memory of each implemented in
+/// `style(port)`. The `$readmemh` file of each port is the
+/// `VRAM_<port>_INIT_FILE` parameter of the module, so that it can be
+/// overridden.
+pub fn generate(screens: &[Memory], style: impl Fn(usize) -> Style) -> Result<String> {
+    let mut parameters = vec![String::from("parameter PORT = 0")];
+    let mut styles = Vec::with_capacity(screens.len());
+    for port in 0..screens.len() {
+        styles.push(match style(port) {
+            Style::Readmemh { file } => {
+                let parameter = format!("VRAM_{port}_INIT_FILE");
+                parameters.push(format!("parameter {parameter} = \"{file}\""));
+                Style::ReadmemhParameter { parameter }
+            }
+            style => style,
+        });
+    }
+
+    let mut verilog = String::with_capacity(30000 * screens.len());
+    verilog.push_str("`default_nettype none\n");
+    verilog.push_str("`timescale 1ns / 1ps\n");
+    verilog.push_str("module vram\n");
+    verilog.push_str("#(\n");
+    verilog.push_str("    // UART port, selecting the power-on screen\n");
+    verilog.push_str(&format!("    {}\n", parameters.join(",\n    ")));
+    verilog.push_str(")\n");
+    verilog.push_str("(\n");
+    verilog.push_str("    input   wire       clk,\n");
+    verilog.push_str("\n");
+    verilog.push_str("    output  wire       read_ready,\n");
+    verilog.push_str("    input   wire       read_valid,\n");
+    verilog.push_str("    input   wire [4:0] read_row,\n");
+    verilog.push_str("    input   wire [6:0] read_col,\n");
+    verilog.push_str("    output  wire [7:0] read_byte,\n");
+    verilog.push_str("\n");
+    verilog.push_str("    output  wire       write_ready,\n");
+    verilog.push_str("    input   wire       write_valid,\n");
+    verilog.push_str("    input   wire [4:0] write_row,\n");
+    verilog.push_str("    input   wire [6:0] write_col,\n");
+    verilog.push_str("    input   wire [7:0] write_byte\n");
+    verilog.push_str(");\n");
+    verilog.push_str("\n");
+    verilog.push_str("    assign read_ready = 1'b1;\n");
+    verilog.push_str("    assign write_ready = (read_valid == 1'b0);\n");
+    verilog.push_str("\n");
+    verilog.push_str("    wire read_enable;\n");
+    verilog.push_str("    wire write_enable;\n");
+    verilog.push_str("    assign read_enable = (read_valid == 1'b1) && (read_ready == 1'b1);\n");
+    verilog.push_str("    assign write_enable = (write_valid == 1'b1) && (write_ready == 1'b1);\n");
+    verilog.push_str("\n");
+    verilog.push_str("    wire [11:0] read_addr = {read_row, read_col};\n");
+    verilog.push_str("    wire [11:0] write_addr = {write_row, write_col};\n");
+    verilog.push_str("\n");
+    let read = Read {
+        clk: "clk",
+        ce: "read_enable",
+        address: "read_addr",
+        q: "read_byte",
+    };
+    let write = Write {
+        clk: "clk",
+        ce: "write_enable",
+        address: "write_addr",
+        data: "write_byte",
+    };
+    verilog.push_str("    generate\n");
+    for (port, memory) in screens.iter().enumerate() {
+        let condition = if port == 0 { "if" } else { "end else if" };
+        verilog.push_str(&format!("        {condition} (PORT == {port}) begin : port_{port}\n"));
+        let mut block = String::new();
+        memory.emit("vram", &styles[port], &read, Some(&write), &mut block)?;
+        // indented into the generate block
+        for line in block.lines() {
+            if !line.is_empty() {
+                verilog.push_str("        ");
+                verilog.push_str(line);
+            }
+            verilog.push_str("\n");
+        }
+    }
+    if !screens.is_empty() {
+        // no screen beyond the ports, rather than an undriven read_byte
+        verilog.push_str("        end else begin : port_none\n");
+        verilog.push_str("            assign read_byte = 8'b0;\n");
+        verilog.push_str("        end\n");
+    }
+    verilog.push_str("    endgenerate\n");
+    verilog.push_str("\n");
+    verilog.push_str("endmodule\n");
+
+    Ok(verilog)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn init_file_parameters() {
+        let placeholders = Placeholders {
+            build_date: String::from("2000-02-29"),
+            git_rev: String::from("v1.0"),
+            baud: 115_200,
+            port: 0,
+            host: String::from("nas"),
+        };
+        let screens = (0..PORTS)
+            .map(|port| memory("{PORT}", &placeholders.for_port(port, "nas")))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        let verilog = generate(&screens, |port| Style::Readmemh { file: format!("vram_{port}.hex") }).unwrap();
+        let (header, body) = verilog.split_once(")\n(\n").unwrap();
+        // in the parameter port list, as parameters of the body are local
+        assert!(header.contains("parameter PORT = 0,\n    parameter VRAM_0_INIT_FILE = \"vram_0.hex\","), "{header}");
+        assert!(header.contains("parameter VRAM_3_INIT_FILE = \"vram_3.hex\"\n"), "{header}");
+        assert!(!body.contains("parameter"), "{body}");
+        assert!(body.contains("$readmemh(VRAM_3_INIT_FILE, vram_data);"), "{body}");
+        assert!(body.contains("end else begin : port_none\n            assign read_byte = 8'b0;"), "{body}");
+        let verilog = generate(&screens, |_| Style::Gowin).unwrap();
+        assert!(verilog.contains("    parameter PORT = 0\n)"), "{verilog}");
+    }
+}
@@ -0,0 +1,385 @@
+//! The power-on screen in `init.txt` format.
+//!
+//! Each line is a VRAM row of ASCII text. `{BUILD_DATE}`, `{GIT_REV}`,
+//! `{BAUD}`, `{PORT}` (0 to 3) and `{HOST}` are replaced by their values,
+//! `{{` and `}}` give literal braces.
+//! Lines between `{center}` and `{end}` are centred on the visible columns,
+//! lines between `{box}` and `{end}` become a centred banner framed with
+//! `+`, `-` and `|`, or with `{box cp437}` the CP437 line drawing codes.
+//!
+//! ```text
+//! {box}
+//! VT4 {GIT_REV}
+//! built {BUILD_DATE}
+//! {end}
+//! {center}
+//! {HOST} on port {PORT}, {BAUD} baud
+//! {end}
+//! ```
+
+use anyhow::{
+    Context,
+    Result,
+    bail,
+};
+use std::path::Path;
+use std::process::Command;
+
+/// VRAM rows and columns.
+pub const ROWS: usize = 32;
+pub const COLUMNS: usize = 128;
+
+/// Columns shown left of the border, see `hdmi_text_mode.sv`.
+pub const VISIBLE_COLUMNS: usize = 100;
+
+/// Values of the placeholders.
+#[derive(Clone, Debug)]
+pub struct Placeholders {
+    /// `YYYY-MM-DD`
+    pub build_date: String,
+    pub git_rev: String,
+    pub baud: u32,
+    /// UART port from 0
+    pub port: usize,
+    pub host: String,
+}
+
+impl Placeholders {
+    /// The values for a build of the repository at `root`: today, or the
+    /// day of `SOURCE_DATE_EPOCH` for a reproducible build, the `git
+    /// describe` of the checkout and the `BAUD` of the ports in `top.sv`.
+    pub fn from_repository(root: &Path) -> Result<Self> {
+        let seconds = match std::env::var("SOURCE_DATE_EPOCH") {
+            Ok(epoch) => epoch.parse()
+                .with_context(|| format!("Invalid SOURCE_DATE_EPOCH '{epoch}'"))?,
+            Err(_) => std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .context("System time before 1970")?
+                .as_secs(),
+        };
+        let git_rev = Command::new("git")
+            .arg("-C").arg(root)
+            .args(["describe", "--always", "--dirty"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+            .unwrap_or_else(|| String::from("unknown"));
+        let top = root.join("fpga/src/top.sv");
+        let verilog = std::fs::read_to_string(&top)
+            .with_context(|| format!("Could not read {}", top.display()))?;
+        Ok(Placeholders {
+            build_date: date(seconds / (24 * 60 * 60)),
+            git_rev,
+            baud: baud(&verilog).with_context(|| format!("{}", top.display()))?,
+            port: 0,
+            host: host_name(0),
+        })
+    }
+
+    /// The values for the screen of `port` connected to `host`.
+    pub fn for_port(&self, port: usize, host: &str) -> Self {
+        Placeholders {
+            port,
+            host: host.to_owned(),
+            ..self.clone()
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<String> {
+        match name {
+            "BUILD_DATE" => Some(self.build_date.clone()),
+            "GIT_REV" => Some(self.git_rev.clone()),
+            "BAUD" => Some(self.baud.to_string()),
+            "PORT" => Some(self.port.to_string()),
+            "HOST" => Some(self.host.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// The host names of `ports` ports from `hosts.txt`, a port number from 0
+/// and the host name per line with `#` comments. Ports not listed are
+/// named `host <port>`.
+pub fn hosts(text: &str, ports: usize) -> Result<Vec<String>> {
+    let mut hosts: Vec<Option<String>> = vec![None; ports];
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (port, host) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let port: usize = port.parse()
+            .with_context(|| format!("Line {}: expecting '<port> <host name>'", number + 1))?;
+        if port >= ports {
+            bail!("Line {}: port {port}, expecting 0 to {}", number + 1, ports - 1);
+        }
+        if hosts[port].replace(host.trim().to_owned()).is_some() {
+            bail!("Line {}: port {port} named twice", number + 1);
+        }
+    }
+    Ok(hosts.into_iter()
+        .enumerate()
+        .map(|(port, host)| host.unwrap_or_else(|| host_name(port)))
+        .collect())
+}
+
+/// Default name of the host on `port`.
+fn host_name(port: usize) -> String {
+    format!("host {port}")
+}
+
+/// The `BAUD` parameter given to the ports, the first `.BAUD(...)`.
+pub fn baud(verilog: &str) -> Result<u32> {
+    let Some((_, rest)) = verilog.split_once(".BAUD(") else {
+        bail!("No .BAUD(...) parameter");
+    };
+    let value = rest.split(')').next().unwrap_or_default();
+    value.replace('_', "").trim().parse()
+        .with_context(|| format!("Invalid BAUD '{value}'"))
+}
+
+/// `YYYY-MM-DD` of `days` since 1970-01-01.
+fn date(days: u64) -> String {
+    // civil from days, proleptic Gregorian calendar in 400 year eras
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era = (day_of_era - (day_of_era / 1460) + (day_of_era / 36_524) - (day_of_era / 146_096)) / 365;
+    let day_of_year = day_of_era - ((365 * year_of_era) + (year_of_era / 4) - (year_of_era / 100));
+    let month = ((5 * day_of_year) + 2) / 153;
+    let day = day_of_year - (((153 * month) + 2) / 5) + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = (era * 400) + year_of_era + u64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Lines of text with their line numbers.
+type Lines = Vec<(usize, String)>;
+
+#[derive(Clone, Copy)]
+enum Block {
+    Center,
+    Box { cp437: bool },
+}
+
+/// The `ROWS` x `COLUMNS` bytes of the screen.
+pub fn parse(init: &str, placeholders: &Placeholders) -> Result<Vec<u8>> {
+    let mut rows: Vec<(usize, Vec<u8>)> = Vec::new();
+    let mut block: Option<(usize, Block, Lines)> = None;
+    for (number, line) in init.lines().enumerate() {
+        let number = number + 1;
+        let directive = match line.trim() {
+            "{center}" => Some(Some(Block::Center)),
+            "{box}" => Some(Some(Block::Box { cp437: false })),
+            "{box cp437}" => Some(Some(Block::Box { cp437: true })),
+            "{end}" => Some(None),
+            _ => None,
+        };
+        match (directive, &mut block) {
+            (Some(Some(_)), Some((first, ..))) => {
+                bail!("Line {number}: block inside the block started at line {first}");
+            }
+            (Some(Some(kind)), None) => {
+                block = Some((number, kind, Vec::new()));
+            }
+            (Some(None), None) => {
+                bail!("Line {number}: {{end}} without {{center}} or {{box}}");
+            }
+            (Some(None), Some(_)) => {
+                let (_, kind, lines) = block.take().expect("block");
+                rows.extend(arrange(kind, &lines)?);
+            }
+            (None, _) => {
+                let text = expand(line, placeholders)
+                    .with_context(|| format!("Line {number}"))?;
+                match &mut block {
+                    Some((_, _, lines)) => lines.push((number, text)),
+                    None => rows.push((number, ascii(number, &text)?)),
+                }
+            }
+        }
+    }
+    if let Some((first, ..)) = block {
+        bail!("Line {first}: block without {{end}}");
+    }
+
+    let mut screen = vec![b' '; ROWS * COLUMNS];
+    for (row, (number, bytes)) in rows.iter().enumerate() {
+        if row >= ROWS {
+            bail!("Line {number}: row {} beyond the {ROWS} VRAM rows", row + 1);
+        }
+        if bytes.len() > COLUMNS {
+            bail!("Line {number}: {} columns, the VRAM has {COLUMNS}", bytes.len());
+        }
+        screen[(row * COLUMNS)..(row * COLUMNS) + bytes.len()].copy_from_slice(bytes);
+    }
+    Ok(screen)
+}
+
+/// `line` with the placeholders replaced.
+fn expand(line: &str, placeholders: &Placeholders) -> Result<String> {
+    let mut expanded = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                expanded.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                expanded.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => bail!("Placeholder {{{name} without '}}', use '{{{{' for a brace"),
+                    }
+                }
+                match placeholders.get(&name) {
+                    Some(value) => expanded.push_str(&value),
+                    None => bail!("Unknown placeholder {{{name}}}, expecting {{BUILD_DATE}}, {{GIT_REV}}, {{BAUD}}, {{PORT}} or {{HOST}}"),
+                }
+            }
+            '}' => bail!("Unmatched '}}', use '}}}}' for a brace"),
+            c => expanded.push(c),
+        }
+    }
+    Ok(expanded)
+}
+
+/// The bytes of `text`, which must be ASCII.
+fn ascii(number: usize, text: &str) -> Result<Vec<u8>> {
+    if let Some((column, c)) = text.chars().enumerate().find(|(_, c)| !c.is_ascii()) {
+        bail!("Line {number} column {}: '{c}' is not ASCII", column + 1);
+    }
+    Ok(text.as_bytes().to_vec())
+}
+
+/// The rows of a block, centred on the visible columns.
+fn arrange(kind: Block, lines: &[(usize, String)]) -> Result<Vec<(usize, Vec<u8>)>> {
+    let mut rows = Vec::new();
+    for (number, text) in lines {
+        rows.push((*number, ascii(*number, text.trim())?));
+    }
+    let widest = rows.iter().map(|(_, bytes)| bytes.len()).max().unwrap_or(0);
+    if let Block::Box { cp437 } = kind {
+        // corners top left, top right, bottom left, bottom right, then
+        // horizontal and vertical lines
+        let [top_left, top_right, bottom_left, bottom_right, horizontal, vertical] = if cp437 {
+            [0xDA, 0xBF, 0xC0, 0xD9, 0xC4, 0xB3]
+        } else {
+            [b'+', b'+', b'+', b'+', b'-', b'|']
+        };
+        // a space either side of the widest line
+        let inner = widest + 2;
+        let first = lines.first().map_or(0, |(number, _)| *number);
+        let last = lines.last().map_or(0, |(number, _)| *number);
+        let mut framed = Vec::new();
+        framed.push((first, [vec![top_left], vec![horizontal; inner], vec![top_right]].concat()));
+        for (number, bytes) in rows {
+            let left = (inner - bytes.len()) / 2;
+            let right = inner - bytes.len() - left;
+            framed.push((number, [vec![vertical], vec![b' '; left], bytes, vec![b' '; right], vec![vertical]].concat()));
+        }
+        framed.push((last, [vec![bottom_left], vec![horizontal; inner], vec![bottom_right]].concat()));
+        rows = framed;
+    }
+    for (number, bytes) in &mut rows {
+        if bytes.len() > VISIBLE_COLUMNS {
+            bail!("Line {number}: {} columns do not fit the {VISIBLE_COLUMNS} visible ones", bytes.len());
+        }
+        let left = (VISIBLE_COLUMNS - bytes.len()) / 2;
+        bytes.splice(0..0, std::iter::repeat_n(b' ', left));
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placeholders() -> Placeholders {
+        Placeholders {
+            build_date: String::from("2000-02-29"),
+            git_rev: String::from("v1.0"),
+            baud: 115_200,
+            port: 1,
+            host: String::from("nas"),
+        }
+    }
+
+    /// Row `row` of `screen` without the trailing spaces.
+    fn row(screen: &[u8], row: usize) -> &str {
+        std::str::from_utf8(&screen[(row * COLUMNS)..((row + 1) * COLUMNS)])
+            .unwrap()
+            .trim_end()
+    }
+
+    #[test]
+    fn rows() {
+        let init = "x\n".repeat(ROWS);
+        assert!(parse(&init, &placeholders()).is_ok());
+        let error = parse(&format!("{init}x\n"), &placeholders()).unwrap_err();
+        assert!(error.to_string().contains("beyond the 32 VRAM rows"), "{error}");
+    }
+
+    #[test]
+    fn columns() {
+        assert!(parse(&"x".repeat(COLUMNS), &placeholders()).is_ok());
+        let error = parse(&"x".repeat(COLUMNS + 1), &placeholders()).unwrap_err();
+        assert!(error.to_string().contains("129 columns"), "{error}");
+    }
+
+    #[test]
+    fn placeholder() {
+        let screen = parse("{GIT_REV} {BUILD_DATE} {BAUD} {HOST}:{PORT}", &placeholders()).unwrap();
+        assert_eq!(row(&screen, 0), "v1.0 2000-02-29 115200 nas:1");
+        let error = parse("\n{FOO}", &placeholders()).unwrap_err();
+        assert_eq!(error.to_string(), "Line 2");
+        assert!(format!("{error:#}").contains("Unknown placeholder {FOO}"), "{error:#}");
+        assert!(parse("{BAUD", &placeholders()).is_err());
+    }
+
+    #[test]
+    fn braces() {
+        let screen = parse("{{BAUD}} {{{BAUD}}}", &placeholders()).unwrap();
+        assert_eq!(row(&screen, 0), "{BAUD} {115200}");
+        assert!(parse("a } b", &placeholders()).is_err());
+    }
+
+    #[test]
+    fn blocks() {
+        let error = parse("text\n{end}", &placeholders()).unwrap_err();
+        assert!(error.to_string().contains("Line 2: {end} without"), "{error}");
+        assert!(parse("{center}\n{box}\n{end}\n{end}", &placeholders()).is_err());
+        assert!(parse("{center}\ntext", &placeholders()).is_err());
+    }
+
+    #[test]
+    fn frame() {
+        let screen = parse("{box}\nab\nc\n{end}\nnext", &placeholders()).unwrap();
+        // the widest line and a space either side, centred on the 100
+        // visible columns
+        let left = " ".repeat((VISIBLE_COLUMNS - 6) / 2);
+        assert_eq!(row(&screen, 0), format!("{left}+----+"));
+        assert_eq!(row(&screen, 1), format!("{left}| ab |"));
+        assert_eq!(row(&screen, 2), format!("{left}| c  |"));
+        assert_eq!(row(&screen, 3), format!("{left}+----+"));
+        assert_eq!(row(&screen, 4), "next");
+        let screen = parse("{box cp437}\nab\n{end}", &placeholders()).unwrap();
+        assert_eq!(&screen[left.len()..left.len() + 6], &[0xDA, 0xC4, 0xC4, 0xC4, 0xC4, 0xBF]);
+    }
+
+    #[test]
+    fn hosts() {
+        let hosts = super::hosts("# port host\n0 router\n3 nas # rack\n", 4).unwrap();
+        assert_eq!(hosts, ["router", "host 1", "host 2", "nas"]);
+        assert!(super::hosts("4 nas", 4).is_err());
+        assert!(super::hosts("1 a\n1 b", 4).is_err());
+    }
+}
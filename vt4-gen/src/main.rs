@@ -0,0 +1,137 @@
+use anyhow::{
+    Context,
+    Result,
+    bail,
+};
+use clap::{
+    Parser,
+    Subcommand,
+};
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use char_rom_gen::rom::{
+    Fonts,
+    Rom,
+};
+use char_rom_gen::source::{
+    self,
+    Cache,
+};
+use gowin_bsram::memory::Style;
+use vram_gen::screen::Placeholders;
+
+/// Root of the repository, holding the generator crates and `fpga`.
+const ROOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/..");
+
+/// Regenerate the generated Verilog sources of the FPGA.
+#[derive(Parser)]
+struct Args {
+    /// Directory of the Verilog sources
+    #[arg(long, default_value = concat!(env!("CARGO_MANIFEST_DIR"), "/../fpga/src"))]
+    fpga: PathBuf,
+
+    /// Report out of date files without writing them
+    #[arg(long)]
+    check: bool,
+
+    /// Print the BSRAM layout and utilization of each memory
+    #[arg(long)]
+    report: bool,
+
+    /// Write behavioural memories loading `$readmemh` hex files to this
+    /// directory instead of the Gowin primitives to --fpga, with the hex and
+    /// Gowin `.mi` files, for simulation
+    #[arg(long)]
+    sim: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, Subcommand)]
+enum Command {
+    /// Character ROM `char_rom.sv` from the vendored fonts
+    CharRom,
+    /// Power-on screens `vram.sv` from `vram-gen/init.txt`, or
+    /// `init-<port>.txt` per port, and `hosts.txt`
+    Vram,
+    /// Scan code ROM `key_code.sv` from `key-code-gen/key-code.txt`
+    KeyCode,
+    /// All of the above
+    All,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let root = Path::new(ROOT);
+
+    let directory = args.sim.as_ref().unwrap_or(&args.fpga);
+    let style = |stem: &str| match &args.sim {
+        Some(_) => Style::Readmemh { file: format!("{stem}.hex") },
+        None => Style::Gowin,
+    };
+
+    let mut outputs = Vec::new();
+    if matches!(args.command, Command::CharRom | Command::All) {
+        let cache = Cache::new(source::FONTS);
+        let mut rom = Rom::load(&cache, &Fonts::default())?;
+        rom.style = style("char_rom");
+        outputs.push(("char_rom", vec![(String::from("char_rom"), rom.memory()?)], rom.verilog()?));
+    }
+    if matches!(args.command, Command::Vram | Command::All) {
+        let placeholders = Placeholders::from_repository(root)?;
+        let screens = vram_gen::screens(&root.join("vram-gen"), &placeholders)?;
+        // one memory and hex file per port
+        let stems: Vec<String> = (0..screens.len()).map(|port| format!("vram_{port}")).collect();
+        let verilog = vram_gen::generate(&screens, |port| style(&stems[port]))?;
+        outputs.push(("vram", stems.into_iter().zip(screens).collect(), verilog));
+    }
+    if matches!(args.command, Command::KeyCode | Command::All) {
+        let keys = read(&root.join("key-code-gen/key-code.txt"))?;
+        outputs.push(("key_code", vec![(String::from("key_code"), key_code_gen::memory(&keys)?)], key_code_gen::generate(&keys, &style("key_code"))?));
+    }
+
+    let mut changed = 0;
+    for (name, memories, verilog) in outputs {
+        let mut files = vec![(format!("{name}.sv"), verilog)];
+        for (stem, memory) in memories {
+            if args.report {
+                let label = if stem == name { format!("{name}.sv") } else { format!("{name}.sv {stem}") };
+                print!("{label}: {}", memory.layout().report());
+            }
+            if args.sim.is_some() {
+                files.push((format!("{stem}.hex"), memory.hex()));
+                files.push((format!("{stem}.mi"), memory.mi()));
+            }
+        }
+        for (name, contents) in files {
+            let path = directory.join(name);
+            let existing = std::fs::read_to_string(&path).ok();
+            if existing.as_deref() == Some(contents.as_str()) {
+                println!("unchanged  {}", path.display());
+                continue;
+            }
+            changed += 1;
+            if args.check {
+                println!("outdated   {}", path.display());
+            } else {
+                std::fs::write(&path, &contents)
+                    .with_context(|| format!("Could not write {}", path.display()))?;
+                println!("changed    {}", path.display());
+            }
+        }
+    }
+    if args.check && changed > 0 {
+        bail!("{changed} generated file(s) out of date");
+    }
+
+    Ok(())
+}
+
+fn read(path: &Path) -> Result<String> {
+    std::fs::read_to_string(path)
+        .with_context(|| format!("Could not read {}", path.display()))
+}